@@ -1,15 +1,22 @@
-use crate::Api;
-use encointer_node_runtime::AccountId;
+use crate::{Api, Result};
+use encointer_node_runtime::{AccountId, Hash};
 use encointer_primitives::{
 	bazaar::{Business, BusinessIdentifier, OfferingData},
 	communities::CommunityIdentifier,
 };
 
-use substrate_api_client::{ac_compose_macros::rpc_params, rpc::Request};
+use parity_scale_codec::Decode;
+use substrate_api_client::{ac_compose_macros::rpc_params, rpc::Request, GetStorage};
 
 #[maybe_async::maybe_async(?Send)]
 pub trait BazaarApi {
 	async fn get_businesses(&self, cid: CommunityIdentifier) -> Option<Vec<Business<AccountId>>>;
+	/// The controllers of the community's businesses, read from the `BusinessRegistry` keys.
+	async fn get_business_accounts(
+		&self,
+		cid: CommunityIdentifier,
+		maybe_at: Option<Hash>,
+	) -> Result<Vec<AccountId>>;
 	async fn get_offerings(&self, cid: CommunityIdentifier) -> Option<Vec<OfferingData>>;
 	async fn get_offerings_for_business(
 		&self,
@@ -27,6 +34,34 @@ impl BazaarApi for Api {
 			.expect("Could not find any businesses...")
 	}
 
+	async fn get_business_accounts(
+		&self,
+		cid: CommunityIdentifier,
+		maybe_at: Option<Hash>,
+	) -> Result<Vec<AccountId>> {
+		let key_prefix = self
+			.get_storage_double_map_key_prefix("EncointerBazaar", "BusinessRegistry", cid)
+			.await?;
+		let page_size = 1000;
+		let mut accounts = Vec::new();
+		let mut start_key = None;
+		loop {
+			let storage_keys = self
+				.get_storage_keys_paged(Some(key_prefix.clone()), page_size, start_key, maybe_at)
+				.await?;
+			for storage_key in storage_keys.iter() {
+				// Blake2_128Concat appends the encoded account to the key
+				let key_postfix = storage_key.as_ref();
+				accounts.push(AccountId::decode(&mut &key_postfix[key_postfix.len() - 32..])?);
+			}
+			if storage_keys.len() < page_size as usize {
+				break
+			}
+			start_key = storage_keys.last().cloned();
+		}
+		Ok(accounts)
+	}
+
 	async fn get_offerings(&self, cid: CommunityIdentifier) -> Option<Vec<OfferingData>> {
 		self.client()
 			.request("encointer_bazaarGetOfferings", rpc_params![cid])
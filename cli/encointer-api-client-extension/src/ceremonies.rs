@@ -1,6 +1,6 @@
-use crate::{Api, CommunitiesApi, Result, SchedulerApi};
+use crate::{haversine_distance, Api, CommunitiesApi, Result, SchedulerApi};
 use encointer_ceremonies_assignment::{
-	assignment_fn_inverse, meetup_index, meetup_location, meetup_time,
+	assignment_fn_inverse, math::find_prime_below, meetup_index, meetup_location, meetup_time,
 };
 use encointer_node_runtime::{CeremonyIndexType, Hash};
 use encointer_primitives::{
@@ -153,6 +153,26 @@ pub trait CeremoniesApi {
 		key: CommunityCeremony,
 		maybe_at: Option<Hash>,
 	) -> ParticipantIndexType;
+	/// Whether the rewards of a meetup have already been issued.
+	async fn is_reward_issued(
+		&self,
+		community_ceremony: &CommunityCeremony,
+		meetup_index: MeetupIndexType,
+		maybe_at: Option<Hash>,
+	) -> Result<bool>;
+	/// Tolerated deviation of an attendance claim from the meetup time in ms.
+	async fn get_time_tolerance(&self, maybe_at: Option<Hash>) -> Result<Moment>;
+	/// Tolerated distance of an attendance claim from the meetup location in m.
+	async fn get_location_tolerance(&self, maybe_at: Option<Hash>) -> Result<u32>;
+	/// Checks an actual position and time against the assigned meetup and the on-chain tolerances.
+	async fn check_claim_plausibility(
+		&self,
+		community_ceremony: &CommunityCeremony,
+		meetup_index: MeetupIndexType,
+		location: Location,
+		time: Moment,
+		maybe_at: Option<Hash>,
+	) -> Result<ClaimPlausibility>;
 }
 
 #[maybe_async::maybe_async(?Send)]
@@ -554,6 +574,104 @@ impl CeremoniesApi for Api {
 		.await
 		.unwrap()
 	}
+
+	async fn is_reward_issued(
+		&self,
+		community_ceremony: &CommunityCeremony,
+		meetup_index: MeetupIndexType,
+		maybe_at: Option<Hash>,
+	) -> Result<bool> {
+		// the value type has changed between pallet versions, we only care about existence.
+		let key = self.metadata().storage_double_map_key(
+			ENCOINTER_CEREMONIES,
+			"IssuedRewards",
+			community_ceremony,
+			meetup_index,
+		)?;
+		Ok(self.get_opaque_storage_by_key(key, maybe_at).await?.is_some())
+	}
+
+	async fn get_time_tolerance(&self, maybe_at: Option<Hash>) -> Result<Moment> {
+		self.get_storage(ENCOINTER_CEREMONIES, "TimeTolerance", maybe_at)
+			.await?
+			.ok_or_else(|| ApiClientError::Other("TimeTolerance not found".into()))
+	}
+
+	async fn get_location_tolerance(&self, maybe_at: Option<Hash>) -> Result<u32> {
+		self.get_storage(ENCOINTER_CEREMONIES, "LocationTolerance", maybe_at)
+			.await?
+			.ok_or_else(|| ApiClientError::Other("LocationTolerance not found".into()))
+	}
+
+	async fn check_claim_plausibility(
+		&self,
+		community_ceremony: &CommunityCeremony,
+		meetup_index: MeetupIndexType,
+		location: Location,
+		time: Moment,
+		maybe_at: Option<Hash>,
+	) -> Result<ClaimPlausibility> {
+		let meetup_location = self
+			.get_meetup_location(community_ceremony, meetup_index, maybe_at)
+			.await?
+			.ok_or_else(|| ApiClientError::Other("meetup location not found".into()))?;
+		let meetup_time = self.get_meetup_time(meetup_location, ONE_DAY, maybe_at).await?;
+		Ok(claim_plausibility(
+			meetup_location,
+			meetup_time,
+			location,
+			time,
+			self.get_location_tolerance(maybe_at).await?,
+			self.get_time_tolerance(maybe_at).await?,
+		))
+	}
+}
+
+/// Deviation of an attendance claim from its meetup, see [`claim_plausibility`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClaimPlausibility {
+	pub meetup_location: Location,
+	pub meetup_time: Moment,
+	pub distance_m: u32,
+	pub location_tolerance_m: u32,
+	/// Claimed time minus meetup time.
+	pub time_deviation_ms: i64,
+	pub time_tolerance_ms: Moment,
+}
+
+impl ClaimPlausibility {
+	pub fn is_location_plausible(&self) -> bool {
+		self.distance_m <= self.location_tolerance_m
+	}
+
+	pub fn is_time_plausible(&self) -> bool {
+		self.time_deviation_ms.unsigned_abs() <= self.time_tolerance_ms
+	}
+
+	pub fn is_plausible(&self) -> bool {
+		self.is_location_plausible() && self.is_time_plausible()
+	}
+}
+
+/// Compares an actual position and time with the meetup, the same way the ceremonies pallet
+/// validates claims. Works offline if the meetup and tolerances are known.
+pub fn claim_plausibility(
+	meetup_location: Location,
+	meetup_time: Moment,
+	location: Location,
+	time: Moment,
+	location_tolerance_m: u32,
+	time_tolerance_ms: Moment,
+) -> ClaimPlausibility {
+	ClaimPlausibility {
+		meetup_location,
+		meetup_time,
+		distance_m: haversine_distance(&meetup_location, &location),
+		location_tolerance_m,
+		time_deviation_ms: time as i64 - meetup_time as i64,
+		time_tolerance_ms,
+	}
 }
 
 async fn get_bootstrapper_or_reputable(
@@ -659,3 +777,167 @@ pub enum RegistrationType {
 	Endorsee,
 	Newbie,
 }
+
+/// Parameters of the meetup assignment, see [`admission_capacity`].
+#[derive(Debug, Clone, Copy)]
+pub struct AssignmentParameters {
+	pub meetup_size_target: u64,
+	pub meetup_min_size: u64,
+	pub meetup_newbie_limit_divider: u64,
+	pub locations: u64,
+	pub bootstrappers: u64,
+	pub reputables: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapacityConstraint {
+	/// There are fewer locations than experienced participants could lead meetups.
+	Locations,
+	/// There are fewer experienced participants than locations.
+	Reputables,
+	/// Seats are left, but newbies are limited to the experienced participants divided by
+	/// `meetup_newbie_limit_divider`. Endorsees can still take the free seats.
+	NewbieLimit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdmissionCapacity {
+	pub max_meetups: u64,
+	/// Maximum endorsees if no newbies register.
+	pub max_endorsees: u64,
+	/// Maximum newbies if no endorsees register.
+	pub max_newbies: u64,
+	/// Meetups resulting from admitting `max_newbies`.
+	pub resulting_meetups: u64,
+	pub binding_constraint: CapacityConstraint,
+	/// Locations to add to seat all registered experienced participants and the newbies they allow.
+	pub suggested_additional_locations: u64,
+	/// Whether all resulting meetups can reach `meetup_min_size`.
+	pub min_size_reached: bool,
+}
+
+/// Computes how many newcomers can be admitted, following the meetup assignment of the
+/// ceremonies pallet: the number of meetups is the smaller of the number of locations and the
+/// largest prime not above the number of experienced participants. Every meetup has at most
+/// `meetup_size_target` participants, bootstrappers are always assigned, then reputables,
+/// endorsees and newbies fill the remaining seats. Newbies are additionally limited to the
+/// number of experienced participants divided by `meetup_newbie_limit_divider`.
+pub fn admission_capacity(p: AssignmentParameters) -> AdmissionCapacity {
+	let experienced_registered = p.bootstrappers + p.reputables;
+	let meetups_by_experienced = find_prime_below(experienced_registered);
+	let max_meetups = p.locations.min(meetups_by_experienced);
+
+	let seats = max_meetups * p.meetup_size_target;
+	let reputables = p.reputables.min(seats.saturating_sub(p.bootstrappers));
+	let experienced = p.bootstrappers + reputables;
+	let free_seats = seats.saturating_sub(experienced);
+	let newbie_limit = experienced / p.meetup_newbie_limit_divider;
+	let max_newbies = free_seats.min(newbie_limit);
+	let participants = experienced + max_newbies;
+	let binding_constraint = if newbie_limit < free_seats {
+		CapacityConstraint::NewbieLimit
+	} else if p.locations < meetups_by_experienced {
+		CapacityConstraint::Locations
+	} else {
+		CapacityConstraint::Reputables
+	};
+
+	// meetups needed to seat all registered experienced participants and as many newbies as
+	// they allow, which is more than the seats of the available locations if these bind
+	let needed_meetups = (experienced_registered +
+		experienced_registered / p.meetup_newbie_limit_divider)
+		.div_ceil(p.meetup_size_target)
+		.min(meetups_by_experienced);
+
+	let resulting_meetups = participants.div_ceil(p.meetup_size_target).min(max_meetups);
+
+	AdmissionCapacity {
+		max_meetups,
+		max_endorsees: free_seats,
+		max_newbies,
+		resulting_meetups,
+		binding_constraint,
+		suggested_additional_locations: needed_meetups.saturating_sub(p.locations),
+		min_size_reached: participants >= resulting_meetups * p.meetup_min_size,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params(locations: u64, bootstrappers: u64, reputables: u64) -> AssignmentParameters {
+		AssignmentParameters {
+			meetup_size_target: 10,
+			meetup_min_size: 3,
+			meetup_newbie_limit_divider: 2,
+			locations,
+			bootstrappers,
+			reputables,
+		}
+	}
+
+	#[test]
+	fn admission_capacity_is_bound_by_locations() {
+		// 3 bootstrappers and 20 reputables could lead 23 meetups
+		let capacity = admission_capacity(params(2, 3, 20));
+		assert_eq!(capacity.binding_constraint, CapacityConstraint::Locations);
+		assert_eq!(capacity.max_meetups, 2);
+		// 20 seats, all taken by experienced participants
+		assert_eq!(capacity.max_newbies, 0);
+		assert_eq!(capacity.max_endorsees, 0);
+		// 23 experienced and 11 newbies need 4 meetups
+		assert_eq!(capacity.suggested_additional_locations, 2);
+	}
+
+	#[test]
+	fn admission_capacity_is_bound_by_experienced_participants() {
+		// 3 bootstrappers and 4 reputables can lead 7 meetups, but only admit 3 newbies
+		let capacity = admission_capacity(params(20, 3, 4));
+		assert_eq!(capacity.binding_constraint, CapacityConstraint::NewbieLimit);
+		assert_eq!(capacity.max_meetups, 7);
+		assert_eq!(capacity.max_endorsees, 63);
+		assert_eq!(capacity.max_newbies, 3);
+		assert_eq!(capacity.resulting_meetups, 1);
+		assert_eq!(capacity.suggested_additional_locations, 0);
+		assert!(capacity.min_size_reached);
+	}
+
+	#[test]
+	fn suggested_locations_seat_all_experienced_participants() {
+		// 55 experienced participants and 27 newbies need 9 meetups, but only fit into one
+		let capacity = admission_capacity(params(1, 5, 50));
+		assert_eq!(capacity.binding_constraint, CapacityConstraint::Locations);
+		assert_eq!(capacity.suggested_additional_locations, 8);
+	}
+
+	#[test]
+	fn admission_capacity_is_bound_by_newbie_limit() {
+		// 11 seats are free, but 9 experienced participants only allow 4 newbies
+		let capacity = admission_capacity(params(2, 3, 6));
+		assert_eq!(capacity.binding_constraint, CapacityConstraint::NewbieLimit);
+		assert_eq!(capacity.max_endorsees, 11);
+		assert_eq!(capacity.max_newbies, 4);
+	}
+
+	#[test]
+	fn admission_capacity_uses_prime_number_of_meetups() {
+		// 8 experienced participants lead at most 7 meetups
+		let capacity = admission_capacity(params(20, 2, 6));
+		assert_eq!(capacity.max_meetups, 7);
+	}
+
+	#[test]
+	fn admission_capacity_is_bound_by_reputables() {
+		// without a newbie limit, 7 experienced participants fill the seats of 7 meetups of 2
+		let capacity = admission_capacity(AssignmentParameters {
+			meetup_size_target: 2,
+			meetup_newbie_limit_divider: 1,
+			..params(20, 3, 4)
+		});
+		assert_eq!(capacity.binding_constraint, CapacityConstraint::Reputables);
+		assert_eq!(capacity.max_newbies, 7);
+		assert_eq!(capacity.suggested_additional_locations, 0);
+	}
+}
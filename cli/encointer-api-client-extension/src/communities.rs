@@ -1,15 +1,27 @@
 use crate::{Api, Result};
-use encointer_node_runtime::Hash;
+use encointer_node_runtime::{Hash, Runtime};
 use encointer_primitives::{
 	balances::{BalanceType, Demurrage},
 	communities::{CidName, CommunityIdentifier, CommunityMetadata, GeoHash, Location},
 };
-use std::str::FromStr;
+use std::{collections::BTreeSet, str::FromStr};
 use substrate_api_client::{ac_compose_macros::rpc_params, rpc::Request, GetStorage};
 
+/// Great circle distance in m, computed like the communities pallet does.
+pub fn haversine_distance(a: &Location, b: &Location) -> u32 {
+	pallet_encointer_communities::Pallet::<Runtime>::haversine_distance(a, b)
+}
+
 #[maybe_async::maybe_async(?Send)]
 pub trait CommunitiesApi {
 	async fn get_locations(&self, cid: CommunityIdentifier) -> Result<Vec<Location>>;
+	/// Like `get_locations`, but read from storage, so it works at any block and without
+	/// offchain indexing.
+	async fn get_locations_at(
+		&self,
+		cid: CommunityIdentifier,
+		maybe_at: Option<Hash>,
+	) -> Result<Vec<Location>>;
 	async fn get_community_identifiers(
 		&self,
 		maybe_at: Option<Hash>,
@@ -35,8 +47,20 @@ pub trait CommunitiesApi {
 		geo_hash: GeoHash,
 		maybe_at: Option<Hash>,
 	) -> Option<Vec<Location>>;
+	async fn get_cids_by_geohash(
+		&self,
+		geo_hash: GeoHash,
+		maybe_at: Option<Hash>,
+	) -> Option<Vec<CommunityIdentifier>>;
 	async fn get_cid_names(&self) -> Option<Vec<CidName>>;
 	async fn verify_cid(&self, cid: &str, maybe_at: Option<Hash>) -> CommunityIdentifier;
+	/// Meetup locations of all communities within `radius_m` of `center`, nearest first.
+	async fn get_locations_nearby(
+		&self,
+		center: Location,
+		radius_m: u32,
+		maybe_at: Option<Hash>,
+	) -> Vec<NearbyLocation>;
 }
 
 #[maybe_async::maybe_async(?Send)]
@@ -48,6 +72,35 @@ impl CommunitiesApi for Api {
 			.await?;
 		Ok(locations)
 	}
+	async fn get_locations_at(
+		&self,
+		cid: CommunityIdentifier,
+		maybe_at: Option<Hash>,
+	) -> Result<Vec<Location>> {
+		let key_prefix = self
+			.get_storage_double_map_key_prefix("EncointerCommunities", "Locations", cid)
+			.await?;
+		let page_size = 1000;
+		let mut locations = Vec::new();
+		let mut start_key = None;
+		loop {
+			let storage_keys = self
+				.get_storage_keys_paged(Some(key_prefix.clone()), page_size, start_key, maybe_at)
+				.await?;
+			for storage_key in storage_keys.iter() {
+				let bucket: Vec<Location> = self
+					.get_storage_by_key(storage_key.clone(), maybe_at)
+					.await?
+					.unwrap_or_default();
+				locations.extend(bucket);
+			}
+			if storage_keys.len() < page_size as usize {
+				break
+			}
+			start_key = storage_keys.last().cloned();
+		}
+		Ok(locations)
+	}
 	async fn get_community_identifiers(
 		&self,
 		maybe_at: Option<Hash>,
@@ -97,6 +150,21 @@ impl CommunitiesApi for Api {
 			.unwrap()
 	}
 
+	async fn get_cids_by_geohash(
+		&self,
+		geo_hash: GeoHash,
+		maybe_at: Option<Hash>,
+	) -> Option<Vec<CommunityIdentifier>> {
+		self.get_storage_map(
+			"EncointerCommunities",
+			"CommunityIdentifiersByGeohash",
+			geo_hash,
+			maybe_at,
+		)
+		.await
+		.unwrap()
+	}
+
 	/// This rpc needs to have offchain indexing enabled in the node.
 	async fn get_cid_names(&self) -> Option<Vec<CidName>> {
 		self.client().request("encointer_getAllCommunities", rpc_params![]).await.expect(
@@ -112,4 +180,114 @@ impl CommunitiesApi for Api {
 		}
 		cid
 	}
+
+	async fn get_locations_nearby(
+		&self,
+		center: Location,
+		radius_m: u32,
+		maybe_at: Option<Hash>,
+	) -> Vec<NearbyLocation> {
+		let mut nearby = Vec::new();
+		for geo_hash in geohashes_within(&center, radius_m) {
+			let cids =
+				self.get_cids_by_geohash(geo_hash.clone(), maybe_at).await.unwrap_or_default();
+			for cid in cids {
+				let locations = self
+					.get_locations_by_geohash(cid, geo_hash.clone(), maybe_at)
+					.await
+					.unwrap_or_default();
+				nearby.extend(locations.into_iter().filter_map(|location| {
+					let distance_m = haversine_distance(&center, &location);
+					(distance_m <= radius_m).then_some(NearbyLocation { cid, location, distance_m })
+				}));
+			}
+		}
+		nearby.sort_by_key(|n| n.distance_m);
+		nearby
+	}
+}
+
+/// A meetup location near some point of interest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearbyLocation {
+	pub cid: CommunityIdentifier,
+	pub location: Location,
+	/// Great-circle distance in m.
+	pub distance_m: u32,
+}
+
+/// Distance in m from `location` to the nearest point of the geohash cell.
+fn distance_to_cell(location: &Location, cell: &GeoHash) -> Option<u32> {
+	let (lon, lat, lon_err, lat_err) = cell.try_as_coordinates().ok()?;
+	let nearest = Location {
+		lat: location.lat.clamp(lat - lat_err, lat + lat_err),
+		lon: location.lon.clamp(lon - lon_err, lon + lon_err),
+	};
+	Some(haversine_distance(location, &nearest))
+}
+
+/// The geohash cells overlapping the circle of `radius_m` around `center`, found by expanding
+/// from the cell of `center` to the neighbours of every overlapping cell.
+pub fn geohashes_within(center: &Location, radius_m: u32) -> Vec<GeoHash> {
+	let Ok(start) = GeoHash::try_from_params(center.lat, center.lon) else { return Vec::new() };
+	let mut seen = BTreeSet::from([start.clone()]);
+	let mut cells = vec![start];
+	let mut i = 0;
+	while i < cells.len() {
+		// fails at the poles and the dateline, where there are no locations
+		if let Ok(n) = cells[i].neighbors() {
+			for cell in [n.n, n.ne, n.e, n.se, n.s, n.sw, n.w, n.nw] {
+				if distance_to_cell(center, &cell).is_some_and(|d| d <= radius_m) &&
+					seen.insert(cell.clone())
+				{
+					cells.push(cell);
+				}
+			}
+		}
+		i += 1;
+	}
+	cells
+}
+
+/// The geohash bucket of `location` and its eight neighbours, which is where the chain looks for
+/// conflicting locations.
+pub fn relevant_geohashes(location: &Location) -> Vec<GeoHash> {
+	let Ok(geohash) = GeoHash::try_from_params(location.lat, location.lon) else {
+		return Vec::new()
+	};
+	let mut geohashes = vec![geohash.clone()];
+	// fails at the poles and the dateline, where the chain rejects locations anyway
+	if let Ok(n) = geohash.neighbors() {
+		geohashes.extend([n.n, n.ne, n.e, n.se, n.s, n.sw, n.w, n.nw]);
+	}
+	geohashes
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use encointer_primitives::communities::Degree;
+
+	fn location(lat: f64, lon: f64) -> Location {
+		Location { lat: Degree::from_num(lat), lon: Degree::from_num(lon) }
+	}
+
+	#[test]
+	fn geohashes_within_cover_the_radius() {
+		let center = location(47.3769, 8.5417);
+		let own = GeoHash::try_from_params(center.lat, center.lon).unwrap();
+		assert_eq!(geohashes_within(&center, 0), vec![own.clone()]);
+
+		let cells = geohashes_within(&center, 10_000);
+		for neighbor in [own.neighbors().unwrap().n, own.neighbors().unwrap().sw] {
+			assert!(cells.contains(&neighbor));
+		}
+		// every location within the radius falls into one of the cells
+		for (dlat, dlon) in [(0.08, 0.0), (-0.08, 0.0), (0.0, 0.12), (0.06, -0.09)] {
+			let l = location(47.3769 + dlat, 8.5417 + dlon);
+			assert!(haversine_distance(&center, &l) <= 10_000);
+			assert!(cells.contains(&GeoHash::try_from_params(l.lat, l.lon).unwrap()));
+		}
+		assert!(cells.iter().all(|c| distance_to_cell(&center, c).unwrap() <= 10_000));
+	}
 }
@@ -21,6 +21,7 @@ pub use ceremonies::*;
 pub use communities::*;
 pub use democracy::*;
 pub use extrinsic_params::*;
+pub use meetup_validation::*;
 pub use reputation_commitments::*;
 pub use reputation_rings::*;
 pub use scheduler::*;
@@ -31,6 +32,7 @@ mod ceremonies;
 mod communities;
 mod democracy;
 mod extrinsic_params;
+mod meetup_validation;
 mod reputation_commitments;
 mod reputation_rings;
 mod scheduler;
@@ -0,0 +1,38 @@
+//! Offline replay of the meetup validation done by the ceremonies pallet when rewards are claimed.
+//!
+//! Participants are referred to by their index in the meetup's participant list.
+
+use encointer_meetup_validation::get_participant_judgements;
+pub use encointer_meetup_validation::{
+	ExcludedParticipant, ExclusionReason, MeetupValidationError, ParticipantJudgements,
+};
+use std::cmp::max;
+
+/// Number of attestations a participant must give and receive, given the number of participants
+/// still considered legit. Same as the threshold applied by `claim_rewards`.
+pub fn attestation_threshold(legit_participants: usize) -> usize {
+	max(
+		if legit_participants > 5 {
+			legit_participants.saturating_sub(2)
+		} else {
+			legit_participants.saturating_sub(1)
+		},
+		1,
+	)
+}
+
+/// Judges all participants of a meetup like `claim_rewards` does.
+///
+/// `votes[i]` is the vote of participant `i` (0 if none) and `attestations[i]` the indices of the
+/// participants attested by participant `i`.
+pub fn judge_meetup(
+	votes: &[u32],
+	attestations: &[Vec<usize>],
+) -> Result<ParticipantJudgements, MeetupValidationError> {
+	get_participant_judgements(
+		&(0..votes.len()).collect(),
+		&votes.to_vec(),
+		&attestations.to_vec(),
+		attestation_threshold,
+	)
+}
@@ -6,6 +6,18 @@ use super::Cli;
 pub enum CeremonyCmd {
 	/// Read current ceremony phase
 	Phase,
+	/// Print phase start times of upcoming cycles and, with --cid, meetup times per location
+	Schedule {
+		/// Number of cycles to compute
+		#[arg(long, default_value = "3")]
+		cycles: u32,
+		/// Export the schedule to this iCalendar file
+		#[arg(long)]
+		ical: Option<String>,
+		/// Scan events from this block on for schedule pushes by one day
+		#[arg(long = "from-block")]
+		from_block: Option<u32>,
+	},
 	/// Read current ceremony index
 	Index,
 	/// Participant-related commands
@@ -31,11 +43,102 @@ pub enum CeremonyCmd {
 		#[arg(long = "ceremony-index", allow_hyphen_values = true)]
 		ceremony_index: Option<i32>,
 	},
+	/// Explain which validation rule decided the reputation of a participant
+	Explain {
+		/// AccountId (SS58)
+		account: String,
+		/// Ceremony index (negative = relative to current)
+		#[arg(long = "ceremony-index", allow_hyphen_values = true)]
+		ceremony_index: Option<i32>,
+	},
+	/// Compute how many newbies and endorsees can be admitted to the next ceremony
+	Capacity {
+		/// Expected number of registering reputables. Defaults to all accounts with reputation within the reputation lifetime
+		#[arg(long)]
+		reputables: Option<u64>,
+	},
+	/// List used and remaining endorsement tickets of bootstrappers and reputables
+	Endorsements {
+		/// Ceremony index (negative = relative to current). Defaults to the cycle endorsements currently count for
+		#[arg(long = "ceremony-index", allow_hyphen_values = true)]
+		ceremony_index: Option<i32>,
+		/// Scan events from this block on to attribute endorsees to their endorsers
+		#[arg(long = "from-block")]
+		from_block: Option<u32>,
+		/// Last block to scan events in. Defaults to the head (or --at)
+		#[arg(long = "to-block")]
+		to_block: Option<u32>,
+	},
+	/// Run ceremonies unattended for keystore accounts (register, attest, claim rewards)
+	Autopilot {
+		/// Accounts to pilot (SS58). Defaults to all sr25519 accounts in the keystore
+		#[arg(long = "accounts", num_args = 1..)]
+		accounts: Vec<String>,
+		/// File to persist the autopilot's progress
+		#[arg(long = "state-file", default_value = "autopilot_state.json")]
+		state_file: String,
+		/// Number of blocks in which failed duties are retried before giving up for the phase
+		#[arg(long = "max-retries", default_value = "10")]
+		max_retries: u32,
+	},
+	/// Export per-community ceremony statistics over a range of cycles. Resumable if --out is given
+	History {
+		/// First ceremony index
+		#[arg(long)]
+		from: u32,
+		/// Last ceremony index. Defaults to the last completed ceremony
+		#[arg(long)]
+		to: Option<u32>,
+		/// Export all communities which existed at each ceremony instead of --cid
+		#[arg(long = "all-communities")]
+		all_communities: bool,
+		/// Output format: csv, json (one object per line) or parquet (requires --out)
+		#[arg(long, default_value = "csv", value_parser = ["csv", "json", "parquet"])]
+		format: String,
+		/// Append rows to this file, skipping ceremonies already present
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Portable proof of attendance bundles
+	#[command(subcommand)]
+	Proof(ProofCmd),
 	/// Admin commands (privileged)
 	#[command(subcommand)]
 	Admin(CeremonyAdminCmd),
 }
 
+#[derive(Subcommand)]
+pub enum ProofCmd {
+	/// Export a signed bundle of proofs of attendance across ceremonies and communities
+	Export {
+		/// Prover account which signs the bundle (SS58)
+		prover: String,
+		/// Accounts whose attendance is proven (SS58). Defaults to the prover
+		#[arg(long = "attendees", num_args = 1..)]
+		attendees: Vec<String>,
+		/// Only include these ceremony indexes. Defaults to all with verified reputation
+		#[arg(long = "ceremony-indexes", num_args = 1..)]
+		ceremony_indexes: Vec<u32>,
+		/// Challenge provided by the verifier, included in the signed payload
+		#[arg(long)]
+		challenge: Option<String>,
+		/// Write the bundle to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Verify a proof of attendance bundle
+	Verify {
+		/// Bundle file (JSON)
+		file: String,
+		/// Expected challenge
+		#[arg(long)]
+		challenge: Option<String>,
+		/// Additionally check participant reputation on chain at the stated ceremony indexes
+		#[arg(long)]
+		onchain: bool,
+	},
+}
+
 #[derive(Subcommand)]
 pub enum ParticipantCmd {
 	/// Register for ceremony
@@ -65,10 +168,10 @@ pub enum ParticipantCmd {
 		#[arg(short = 's', long)]
 		signer: Option<String>,
 	},
-	/// Endorse newcomers with a bootstrapper account
+	/// Endorse newcomers with a bootstrapper or reputable account
 	Endorse {
-		/// Bootstrapper account (SS58)
-		bootstrapper: String,
+		/// Bootstrapper or reputable account (SS58)
+		endorser: String,
 		/// Endorsee account(s) (SS58)
 		#[arg(short = 'e', long = "endorsees", required = true, num_args = 1..)]
 		endorsees: Vec<String>,
@@ -87,6 +190,21 @@ pub enum ParticipantCmd {
 		account: String,
 		/// Vote on number of people present
 		vote: u32,
+		/// Actual latitude of the participant. Without position, the meetup location is claimed
+		#[arg(long, allow_hyphen_values = true, requires = "lon")]
+		lat: Option<f64>,
+		/// Actual longitude of the participant
+		#[arg(long, allow_hyphen_values = true, requires = "lat")]
+		lon: Option<f64>,
+		/// Actual time of the participant as unix timestamp in ms. Defaults to the local clock
+		#[arg(long, requires = "lat")]
+		time: Option<u64>,
+		/// JSON file with the actual position: {"lat": .., "lon": .., "time": ..}
+		#[arg(long = "position-file", conflicts_with_all = ["lat", "lon", "time"])]
+		position_file: Option<String>,
+		/// Sign the claim even if it is outside the on-chain tolerances
+		#[arg(long)]
+		force: bool,
 	},
 	/// Claim meetup rewards
 	ClaimReward {
@@ -96,9 +214,12 @@ pub enum ParticipantCmd {
 		/// Meetup index to claim for
 		#[arg(long)]
 		meetup_index: Option<u64>,
-		/// Claim for all meetups
+		/// Claim for all meetups with outstanding rewards and report the outcome
 		#[arg(short = 'a', long)]
 		all: bool,
+		/// With --all, also report unclaimed meetups of this many ceremonies before
+		#[arg(long, default_value = "0")]
+		lookback: u32,
 	},
 	/// List registered participants
 	List {
@@ -148,9 +269,14 @@ pub enum CeremonyAdminCmd {
 
 impl CeremonyCmd {
 	pub async fn run(&self, cli: &Cli) {
-		use crate::commands::{encointer_ceremonies, encointer_scheduler};
+		use crate::commands::{
+			encointer_autopilot, encointer_ceremonies, encointer_history, encointer_scheduler,
+		};
 		match self {
 			Self::Phase => encointer_scheduler::get_phase(cli).await,
+			Self::Schedule { cycles, ical, from_block } =>
+				encointer_scheduler::print_schedule(cli, *cycles, ical.as_deref(), *from_block)
+					.await,
 			Self::Index => encointer_scheduler::get_cindex(cli).await,
 			Self::Participant(cmd) => cmd.run(cli).await,
 			Self::ListMeetups { ceremony_index } =>
@@ -160,6 +286,31 @@ impl CeremonyCmd {
 			Self::ListReputables => encointer_ceremonies::list_reputables(cli).await,
 			Self::Stats { ceremony_index } =>
 				encointer_ceremonies::print_ceremony_stats(cli, *ceremony_index).await,
+			Self::History { from, to, all_communities, format, out } =>
+				encointer_history::export_ceremony_history(
+					cli,
+					*from,
+					*to,
+					*all_communities,
+					format,
+					out.as_deref(),
+				)
+				.await,
+			Self::Proof(cmd) => cmd.run(cli).await,
+			Self::Explain { account, ceremony_index } =>
+				encointer_ceremonies::explain_reputation(cli, account, *ceremony_index).await,
+			Self::Capacity { reputables } =>
+				encointer_ceremonies::print_admission_capacity(cli, *reputables).await,
+			Self::Endorsements { ceremony_index, from_block, to_block } =>
+				encointer_ceremonies::list_endorsements(
+					cli,
+					*ceremony_index,
+					*from_block,
+					*to_block,
+				)
+				.await,
+			Self::Autopilot { accounts, state_file, max_retries } =>
+				encointer_autopilot::run_autopilot(cli, accounts, state_file, *max_retries).await,
 			Self::Admin(cmd) => cmd.run(cli).await,
 		}
 	}
@@ -181,15 +332,30 @@ impl ParticipantCmd {
 				.await,
 			Self::Upgrade { account, signer } =>
 				encointer_ceremonies::upgrade_registration(cli, account, signer.as_deref()).await,
-			Self::Endorse { bootstrapper, endorsees } =>
-				encointer_ceremonies::endorse(cli, bootstrapper, endorsees).await,
+			Self::Endorse { endorser, endorsees } =>
+				encointer_ceremonies::endorse(cli, endorser, endorsees).await,
 			Self::Attest { account, attestees } =>
 				encointer_ceremonies::attest_attendees(cli, account, attestees).await,
-			Self::NewClaim { account, vote } =>
-				encointer_ceremonies::new_claim(cli, account, *vote).await,
-			Self::ClaimReward { signer, meetup_index, all } =>
-				encointer_ceremonies::claim_reward(cli, signer.as_deref(), *meetup_index, *all)
-					.await,
+			Self::NewClaim { account, vote, lat, lon, time, position_file, force } =>
+				encointer_ceremonies::new_claim(
+					cli,
+					account,
+					*vote,
+					lat.zip(*lon),
+					*time,
+					position_file.as_deref(),
+					*force,
+				)
+				.await,
+			Self::ClaimReward { signer, meetup_index, all, lookback } =>
+				encointer_ceremonies::claim_reward(
+					cli,
+					signer.as_deref(),
+					*meetup_index,
+					*all,
+					*lookback,
+				)
+				.await,
 			Self::List { ceremony_index } =>
 				encointer_ceremonies::list_participants(cli, *ceremony_index).await,
 			Self::Reputation { account } => encointer_ceremonies::reputation(cli, account).await,
@@ -214,3 +380,24 @@ impl CeremonyAdminCmd {
 		}
 	}
 }
+
+impl ProofCmd {
+	pub async fn run(&self, cli: &Cli) {
+		use crate::commands::encointer_proofs;
+		match self {
+			Self::Export { prover, attendees, ceremony_indexes, challenge, out } =>
+				encointer_proofs::export_proof_bundle(
+					cli,
+					prover,
+					attendees,
+					ceremony_indexes,
+					challenge.as_deref(),
+					out.as_deref(),
+				)
+				.await,
+			Self::Verify { file, challenge, onchain } =>
+				encointer_proofs::verify_proof_bundle(cli, file, challenge.as_deref(), *onchain)
+					.await,
+		}
+	}
+}
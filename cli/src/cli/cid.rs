@@ -0,0 +1,42 @@
+use clap::Subcommand;
+
+use super::Cli;
+
+#[derive(Subcommand)]
+pub enum CidCmd {
+	/// Compute the community identifier from the first location and the bootstrappers
+	Compute {
+		/// First location of the community as lat,lon
+		#[arg(long, allow_hyphen_values = true)]
+		location: String,
+		/// Bootstrappers (SS58 or dev seeds like //Alice), in the order of the spec
+		#[arg(long, num_args = 1.., required = true)]
+		bootstrappers: Vec<String>,
+	},
+	/// Show the geohash cell and digest of a community identifier
+	Decode {
+		/// Community identifier, base58 encoded
+		cid: String,
+	},
+	/// Assemble a community identifier from its geohash and digest
+	Encode {
+		/// Geohash with 5 characters
+		#[arg(long)]
+		geohash: String,
+		/// Digest of the bootstrappers, 4 bytes hex
+		#[arg(long)]
+		digest: String,
+	},
+}
+
+impl CidCmd {
+	pub async fn run(&self, _cli: &Cli) {
+		use crate::commands::encointer_cid;
+		match self {
+			Self::Compute { location, bootstrappers } =>
+				encointer_cid::compute_cid(location, bootstrappers),
+			Self::Decode { cid } => encointer_cid::decode_cid(cid),
+			Self::Encode { geohash, digest } => encointer_cid::encode_cid(geohash, digest),
+		}
+	}
+}
@@ -1,4 +1,4 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 
 use super::Cli;
 
@@ -25,18 +25,272 @@ pub enum CommunityCmd {
 	List,
 	/// Query total issuance for community (requires --cid)
 	Issuance,
+	/// Report inactivity, registration trends and treasury of communities as JSON
+	///
+	/// Reports all communities unless --cid is given.
+	Health {
+		/// Number of past ceremonies to report on
+		#[arg(long, default_value = "3")]
+		cycles: u32,
+		/// Flag inactive communities which are purged after this many more inactive ceremonies
+		#[arg(long = "warn-cycles", default_value = "2")]
+		warn_cycles: u32,
+	},
+	/// List communities and meetup locations near a point, nearest first
+	Nearby {
+		/// Latitude in degrees
+		#[arg(long, allow_hyphen_values = true)]
+		lat: f64,
+		/// Longitude in degrees
+		#[arg(long, allow_hyphen_values = true)]
+		lon: f64,
+		/// Search radius in km
+		#[arg(long = "radius-km", default_value = "10")]
+		radius_km: f64,
+	},
+	/// Time series of transfers, issuance and fees of a community currency as JSON (requires --cid)
+	///
+	/// Scans the events of every block in the range, which may take a while.
+	Analytics {
+		/// First block to scan
+		#[arg(long = "from-block")]
+		from_block: u32,
+		/// Last block to scan. Defaults to the head (or --at)
+		#[arg(long = "to-block")]
+		to_block: Option<u32>,
+		/// Number of blocks per period of the time series
+		#[arg(long, default_value = "14400")]
+		period: u32,
+	},
+	/// Export all holders of a community currency with demurrage applied (requires --cid)
+	///
+	/// Balances are computed at --at or the latest block. Totals are reconciled against the
+	/// total issuance on stderr.
+	Holders {
+		/// Output format
+		#[arg(long, default_value = "csv", value_parser = ["csv", "json"])]
+		format: String,
+		/// Write the export to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Export a registered community as enhanced geojson spec (requires --cid)
+	///
+	/// The result passes `community spec lint --offline` and can be used to register the same
+	/// community on another chain.
+	Export {
+		/// Write the spec to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Community spec file commands
+	#[command(subcommand)]
+	Spec(SpecCmd),
 	/// Location management commands
 	#[command(subcommand)]
 	Location(LocationCmd),
 	/// Treasury commands
 	#[command(subcommand)]
 	Treasury(TreasuryCmd),
+	/// Admin commands (privileged)
+	#[command(subcommand)]
+	Admin(CommunityAdminCmd),
+	/// Community asset commands
+	#[command(subcommand)]
+	Assets(AssetsCmd),
+	/// Community announcement commands
+	#[command(subcommand)]
+	Announce(AnnounceCmd),
+}
+
+#[derive(Subcommand)]
+pub enum AnnounceCmd {
+	/// Sign an announcement with the community's announcement signer key (requires --cid)
+	Sign {
+		/// File with the hex encoded BIP340 secret key of the announcement signer.
+		/// Defaults to the key in $ENCOINTER_ANNOUNCEMENT_SECRET_KEY
+		#[arg(long = "secret-key-file")]
+		secret_key_file: Option<String>,
+		#[arg(long)]
+		title: String,
+		#[arg(long)]
+		body: String,
+		/// Language of title and body, e.g. en or de-CH
+		#[arg(long, default_value = "en")]
+		locale: String,
+		/// Start of validity (RFC 3339). Defaults to now
+		#[arg(long = "valid-from")]
+		valid_from: Option<String>,
+		/// End of validity (RFC 3339)
+		#[arg(long = "valid-until")]
+		valid_until: String,
+		/// IPFS cid of an attachment
+		#[arg(long)]
+		attachment: Option<String>,
+		/// Write the announcement to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Verify an announcement against the community's on-chain announcement signer
+	Verify {
+		/// Announcement file (JSON)
+		file: String,
+		/// Only check the signature and validity window
+		#[arg(long)]
+		offline: bool,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum AssetsCmd {
+	/// Validate an asset folder and upload it to IPFS via authenticated gateway (requires --cid)
+	Publish {
+		/// Folder with icons/ and theme.json
+		folder: String,
+		/// Account to authenticate (must be CC holder)
+		#[arg(short = 's', long)]
+		signer: String,
+		/// IPFS auth gateway URL
+		#[arg(long, default_value = "http://localhost:5050")]
+		gateway: String,
+		/// Set community.meta.assets in this spec file to the resulting cid
+		#[arg(long = "patch-spec")]
+		patch_spec: Option<String>,
+		/// Only validate the folder
+		#[arg(short = 'd', long)]
+		dryrun: bool,
+	},
+}
+
+/// Options for submitting a privileged call.
+#[derive(Args)]
+pub struct PrivilegedCallArgs {
+	/// Account with necessary privileges
+	#[arg(short = 's', long)]
+	pub signer: Option<String>,
+	/// Print encoded call instead of sending
+	#[arg(short = 'd', long)]
+	pub dryrun: bool,
+	/// Call wrapping: none|sudo|collective
+	#[arg(short = 'w', long = "wrap-call", default_value = "none")]
+	pub wrap_call: String,
+}
+
+#[derive(Subcommand)]
+pub enum CommunityAdminCmd {
+	/// Update the community metadata to the one in a spec file (--cid defaults to the spec's)
+	UpdateMetadata {
+		/// Enhanced geojson file specifying the community
+		specfile: String,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+	},
+	/// Set the demurrage of a community (requires --cid)
+	SetDemurrage {
+		/// Demurrage halving blocks
+		demurrage_halving_blocks: u64,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+	},
+	/// Set the nominal income per ceremony of a community (requires --cid)
+	SetNominalIncome {
+		/// Nominal income
+		nominal_income: f64,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+	},
+	/// Set the minimal solar trip time between locations of all communities
+	SetMinSolarTripTime {
+		/// Minimal solar trip time in seconds
+		seconds: u32,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+	},
+	/// Set the maximal travel speed assumed between locations of all communities
+	SetMaxSpeed {
+		/// Maximal speed in meters per second
+		mps: u32,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+	},
+	/// Remove a community with all its locations (requires --cid)
+	Purge {
+		/// Submit the purge. Without it, only the community to be removed is shown
+		#[arg(long)]
+		yes: bool,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+	},
+}
+
+#[derive(Subcommand)]
+pub enum SpecCmd {
+	/// Check a community spec for errors before registering it
+	Lint {
+		/// Enhanced geojson file specifying the community
+		specfile: String,
+		/// Skip the checks against chain state
+		#[arg(long)]
+		offline: bool,
+	},
+	/// Print the JSON Schema of the community spec format
+	Schema {
+		/// Write the schema to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Upgrade a community spec to the current format version
+	Migrate {
+		/// Enhanced geojson file specifying the community
+		specfile: String,
+		/// Write the upgraded spec to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
 }
 
 #[derive(Subcommand)]
 pub enum LocationCmd {
 	/// List all meetup locations for a community
 	List,
+	/// Select well-spread meetup locations from a geojson of public places, offline
+	Suggest {
+		/// Geojson with candidate places as points, e.g. parks, squares and cafés from OSM
+		places: String,
+		/// Geojson with the Polygon or MultiPolygon to place locations within
+		#[arg(long)]
+		within: String,
+		/// Spec or locations files whose locations must be avoided, e.g. from `community export`.
+		/// Pass the files of all communities in the area, as locations must keep their distance to
+		/// the locations of every community
+		#[arg(long, num_args = 1..)]
+		existing: Vec<String>,
+		/// Maximum number of locations to select
+		#[arg(long, default_value = "10")]
+		count: usize,
+		/// Minimum distance between locations in m
+		#[arg(long = "min-distance-m", default_value = "500")]
+		min_distance_m: u32,
+		/// The chain's MinSolarTripTimeS
+		#[arg(long = "min-solar-trip-time-s", default_value = "1")]
+		min_solar_trip_time_s: u32,
+		/// The chain's MaxSpeedMps
+		#[arg(long = "max-speed-mps", default_value = "1")]
+		max_speed_mps: u32,
+		/// Write the locations to this file instead of stdout
+		#[arg(long)]
+		out: Option<String>,
+	},
+	/// Add and remove locations so the community's locations match a spec file (requires --cid)
+	Sync {
+		/// Geojson file with locations as points
+		specfile: String,
+		#[command(flatten)]
+		call: PrivilegedCallArgs,
+		/// Maximum batch size
+		#[arg(long = "batch-size", default_value = "100")]
+		batch_size: u32,
+	},
 	/// Register new locations for a community
 	Add {
 		/// Geojson file with locations as points
@@ -104,7 +358,10 @@ pub enum SwapOptionCmd {
 
 impl CommunityCmd {
 	pub async fn run(&self, cli: &Cli) {
-		use crate::commands::{encointer_communities, encointer_core};
+		use crate::commands::{
+			encointer_analytics, encointer_communities, encointer_community_health, encointer_core,
+			encointer_holders,
+		};
 		match self {
 			Self::New { specfile, signer, dryrun, wrap_call, batch_size } =>
 				encointer_communities::new_community(
@@ -118,17 +375,193 @@ impl CommunityCmd {
 				.await,
 			Self::List => encointer_communities::list_communities(cli).await,
 			Self::Issuance => encointer_core::issuance(cli).await,
+			Self::Health { cycles, warn_cycles } =>
+				encointer_community_health::print_community_health(cli, *cycles, *warn_cycles).await,
+			Self::Nearby { lat, lon, radius_km } =>
+				encointer_communities::list_nearby(cli, *lat, *lon, *radius_km).await,
+			Self::Analytics { from_block, to_block, period } =>
+				encointer_analytics::print_analytics(cli, *from_block, *to_block, *period).await,
+			Self::Holders { format, out } =>
+				encointer_holders::export_holders(cli, format, out.as_deref()).await,
+			Self::Export { out } =>
+				encointer_communities::export_community(cli, out.as_deref()).await,
+			Self::Spec(cmd) => cmd.run(cli).await,
+			Self::Admin(cmd) => cmd.run(cli).await,
+			Self::Assets(cmd) => cmd.run(cli).await,
+			Self::Announce(cmd) => cmd.run(cli).await,
 			Self::Location(cmd) => cmd.run(cli).await,
 			Self::Treasury(cmd) => cmd.run(cli).await,
 		}
 	}
 }
 
-impl LocationCmd {
+impl AnnounceCmd {
+	pub async fn run(&self, cli: &Cli) {
+		use crate::commands::encointer_announcements;
+		match self {
+			Self::Sign {
+				secret_key_file,
+				title,
+				body,
+				locale,
+				valid_from,
+				valid_until,
+				attachment,
+				out,
+			} => encointer_announcements::sign_announcement(
+				cli,
+				secret_key_file.as_deref(),
+				title,
+				body,
+				locale,
+				valid_from.as_deref(),
+				valid_until,
+				attachment.as_deref(),
+				out.as_deref(),
+			),
+			Self::Verify { file, offline } =>
+				encointer_announcements::verify_announcement(cli, file, *offline).await,
+		}
+	}
+}
+
+impl AssetsCmd {
+	pub async fn run(&self, cli: &Cli) {
+		use crate::commands::encointer_ipfs;
+		match self {
+			Self::Publish { folder, signer, gateway, patch_spec, dryrun } =>
+				encointer_ipfs::publish_assets(
+					cli,
+					signer,
+					gateway,
+					folder,
+					patch_spec.as_deref(),
+					*dryrun,
+				)
+				.await,
+		}
+	}
+}
+
+impl CommunityAdminCmd {
 	pub async fn run(&self, cli: &Cli) {
 		use crate::commands::encointer_communities;
+		match self {
+			Self::UpdateMetadata { specfile, call } =>
+				encointer_communities::update_community_metadata(
+					cli,
+					specfile,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+				)
+				.await,
+			Self::SetDemurrage { demurrage_halving_blocks, call } =>
+				encointer_communities::set_demurrage(
+					cli,
+					*demurrage_halving_blocks,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+				)
+				.await,
+			Self::SetNominalIncome { nominal_income, call } =>
+				encointer_communities::set_nominal_income(
+					cli,
+					*nominal_income,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+				)
+				.await,
+			Self::SetMinSolarTripTime { seconds, call } =>
+				encointer_communities::set_communities_parameter(
+					cli,
+					"MinSolarTripTimeS",
+					"set_min_solar_trip_time_s",
+					*seconds,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+				)
+				.await,
+			Self::SetMaxSpeed { mps, call } =>
+				encointer_communities::set_communities_parameter(
+					cli,
+					"MaxSpeedMps",
+					"set_max_speed_mps",
+					*mps,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+				)
+				.await,
+			Self::Purge { yes, call } =>
+				encointer_communities::purge_community(
+					cli,
+					*yes,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+				)
+				.await,
+		}
+	}
+}
+
+impl SpecCmd {
+	pub async fn run(&self, cli: &Cli) {
+		use crate::commands::encointer_communities;
+		match self {
+			Self::Lint { specfile, offline } =>
+				encointer_communities::lint_community_spec(cli, specfile, *offline).await,
+			Self::Schema { out } =>
+				encointer_communities::print_community_spec_schema(out.as_deref()),
+			Self::Migrate { specfile, out } =>
+				encointer_communities::migrate_community_spec_file(specfile, out.as_deref()),
+		}
+	}
+}
+
+impl LocationCmd {
+	pub async fn run(&self, cli: &Cli) {
+		use crate::commands::{
+			encointer_communities,
+			encointer_location_suggest::{self, LocationConstraints},
+		};
 		match self {
 			Self::List => encointer_communities::list_locations(cli).await,
+			Self::Suggest {
+				places,
+				within,
+				existing,
+				count,
+				min_distance_m,
+				min_solar_trip_time_s,
+				max_speed_mps,
+				out,
+			} => encointer_location_suggest::suggest_locations(
+				places,
+				within,
+				existing,
+				LocationConstraints {
+					min_solar_trip_time_s: *min_solar_trip_time_s,
+					max_speed_mps: *max_speed_mps,
+					min_distance_m: *min_distance_m,
+				},
+				*count,
+				out.as_deref(),
+			),
+			Self::Sync { specfile, call, batch_size } =>
+				encointer_communities::sync_locations(
+					cli,
+					specfile,
+					call.signer.as_deref(),
+					call.dryrun,
+					&call.wrap_call,
+					*batch_size,
+				)
+				.await,
 			Self::Add { specfile, signer, dryrun } =>
 				encointer_communities::add_locations(cli, specfile, signer.as_deref(), *dryrun)
 					.await,
@@ -1,4 +1,4 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 
 use super::Cli;
 
@@ -12,6 +12,23 @@ pub enum DemocracyCmd {
 	Proposal(ProposalCmd),
 	/// List enactment queue
 	EnactmentQueue,
+	/// Forecast the outcome of a proposal under adaptive quorum biasing
+	///
+	/// Any of --ayes, --turnout and --electorate replace the on-chain values for a what-if
+	/// analysis.
+	Forecast {
+		/// Proposal ID
+		proposal_id: u128,
+		/// Hypothetical number of aye votes
+		#[arg(long)]
+		ayes: Option<u128>,
+		/// Hypothetical number of votes cast
+		#[arg(long)]
+		turnout: Option<u128>,
+		/// Hypothetical electorate size, instead of the one fixed at submission
+		#[arg(long)]
+		electorate: Option<u128>,
+	},
 	/// Submit a vote
 	Vote {
 		/// AccountId (SS58)
@@ -21,7 +38,14 @@ pub enum DemocracyCmd {
 		/// Vote: aye or nay
 		vote: String,
 		/// Reputation: cid1_cindex1,cid2_cindex2,...
-		reputation_vec: String,
+		#[arg(required_unless_present = "auto_reputation")]
+		reputation_vec: Option<String>,
+		/// Vote with all eligible reputations which haven't been used for this proposal yet
+		#[arg(long = "auto-reputation", conflicts_with = "reputation_vec")]
+		auto_reputation: bool,
+		/// Only show the reputations and resulting voting weight, don't vote
+		#[arg(short = 'd', long)]
+		dryrun: bool,
 	},
 }
 
@@ -48,6 +72,35 @@ pub enum ProposeCmd {
 		/// Demurrage halving blocks
 		demurrage_halving_blocks: u64,
 	},
+	/// Submit proposal to add a meetup location to a community (requires --cid)
+	AddLocation {
+		/// AccountId (SS58)
+		account: String,
+		/// Latitude in degrees
+		#[arg(long, allow_hyphen_values = true)]
+		lat: f64,
+		/// Longitude in degrees
+		#[arg(long, allow_hyphen_values = true)]
+		lon: f64,
+	},
+	/// Submit proposal to remove a meetup location from a community (requires --cid)
+	RemoveLocation {
+		/// AccountId (SS58)
+		account: String,
+		/// Latitude in degrees
+		#[arg(long, allow_hyphen_values = true)]
+		lat: f64,
+		/// Longitude in degrees
+		#[arg(long, allow_hyphen_values = true)]
+		lon: f64,
+	},
+	/// Submit proposal to update community metadata from a spec file (requires --cid)
+	UpdateCommunityMetadata {
+		/// AccountId (SS58)
+		account: String,
+		/// Enhanced geojson file whose meta section is proposed
+		specfile: String,
+	},
 	/// Submit a petition
 	Petition {
 		/// AccountId (SS58)
@@ -64,6 +117,17 @@ pub enum ProposeCmd {
 		/// Amount
 		amount: u128,
 	},
+	/// Submit spend asset proposal (from the community treasury if --cid is given)
+	SpendAsset {
+		/// AccountId (SS58)
+		account: String,
+		/// Beneficiary (SS58)
+		to: String,
+		/// Amount
+		amount: u128,
+		#[command(flatten)]
+		asset: AssetArgs,
+	},
 	/// Submit proposal to issue a swap native option
 	IssueSwapNativeOption {
 		/// AccountId (SS58)
@@ -92,9 +156,8 @@ pub enum ProposeCmd {
 		account: String,
 		/// Beneficiary (SS58)
 		to: String,
-		/// SCALE-encoded VersionedLocatableAsset (hex)
-		#[arg(long = "asset-id")]
-		asset_id: String,
+		#[command(flatten)]
+		asset: AssetArgs,
 		/// Total asset token allowance
 		#[arg(long = "asset-allowance")]
 		asset_allowance: u128,
@@ -113,6 +176,29 @@ pub enum ProposeCmd {
 	},
 }
 
+/// Asset held on a sibling chain, e.g. an asset of Asset Hub's `Assets` pallet.
+#[derive(Args)]
+pub struct AssetArgs {
+	/// Parachain id of the chain holding the asset
+	#[arg(long = "asset-para-id", default_value = "1000")]
+	pub para_id: u32,
+	/// Pallet instance of the assets pallet on that chain
+	#[arg(long = "asset-pallet-instance", default_value = "50")]
+	pub pallet_instance: u8,
+	/// General index (asset id) within the assets pallet. Omit for the relay chain's native token
+	#[arg(long = "asset-general-index")]
+	pub general_index: Option<u128>,
+	/// XCM version to encode the asset location with: 3, 4 or 5
+	#[arg(long = "xcm-version", default_value = "5")]
+	pub xcm_version: u32,
+	/// SCALE-encoded VersionedLocatableAsset (hex), instead of the typed asset arguments
+	#[arg(
+		long = "asset-id",
+		conflicts_with_all = ["para_id", "pallet_instance", "general_index", "xcm_version"]
+	)]
+	pub asset_id: Option<String>,
+}
+
 #[derive(Subcommand)]
 pub enum ProposalCmd {
 	/// List proposals
@@ -137,8 +223,26 @@ impl DemocracyCmd {
 			Self::Propose(cmd) => cmd.run(cli).await,
 			Self::Proposal(cmd) => cmd.run(cli).await,
 			Self::EnactmentQueue => encointer_democracy::list_enactment_queue(cli).await,
-			Self::Vote { account, proposal_id, vote, reputation_vec } =>
-				encointer_democracy::vote(cli, account, *proposal_id, vote, reputation_vec).await,
+			Self::Forecast { proposal_id, ayes, turnout, electorate } =>
+				encointer_democracy::forecast_proposal(
+					cli,
+					*proposal_id,
+					*ayes,
+					*turnout,
+					*electorate,
+				)
+				.await,
+			Self::Vote { account, proposal_id, vote, reputation_vec, auto_reputation, dryrun } =>
+				encointer_democracy::vote(
+					cli,
+					account,
+					*proposal_id,
+					vote,
+					reputation_vec.as_deref(),
+					*auto_reputation,
+					*dryrun,
+				)
+				.await,
 		}
 	}
 }
@@ -168,10 +272,22 @@ impl ProposeCmd {
 					*demurrage_halving_blocks,
 				)
 				.await,
+			Self::AddLocation { account, lat, lon } =>
+				encointer_democracy::submit_add_location_proposal(cli, account, *lat, *lon).await,
+			Self::RemoveLocation { account, lat, lon } =>
+				encointer_democracy::submit_remove_location_proposal(cli, account, *lat, *lon).await,
+			Self::UpdateCommunityMetadata { account, specfile } =>
+				encointer_democracy::submit_update_community_metadata_proposal(
+					cli, account, specfile,
+				)
+				.await,
 			Self::Petition { account, demand } =>
 				encointer_democracy::submit_petition(cli, account, demand).await,
 			Self::SpendNative { account, to, amount } =>
 				encointer_democracy::submit_spend_native_proposal(cli, account, to, *amount).await,
+			Self::SpendAsset { account, to, amount, asset } =>
+				encointer_democracy::submit_spend_asset_proposal(cli, account, to, *amount, asset)
+					.await,
 			Self::IssueSwapNativeOption {
 				account,
 				to,
@@ -195,7 +311,7 @@ impl ProposeCmd {
 			Self::IssueSwapAssetOption {
 				account,
 				to,
-				asset_id,
+				asset,
 				asset_allowance,
 				rate,
 				do_burn,
@@ -206,7 +322,7 @@ impl ProposeCmd {
 					cli,
 					account,
 					to,
-					asset_id,
+					asset,
 					*asset_allowance,
 					*rate,
 					*do_burn,
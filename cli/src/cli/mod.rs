@@ -2,6 +2,7 @@ mod account;
 mod bazaar;
 mod ceremony;
 mod chain;
+mod cid;
 mod community;
 mod democracy;
 mod faucet;
@@ -13,6 +14,7 @@ pub use account::*;
 pub use bazaar::*;
 pub use ceremony::*;
 pub use chain::*;
+pub use cid::*;
 pub use community::*;
 pub use democracy::*;
 pub use faucet::*;
@@ -80,6 +82,9 @@ pub enum Commands {
 	/// Ceremony-related commands
 	#[command(subcommand)]
 	Ceremony(CeremonyCmd),
+	/// Offline community identifier tools
+	#[command(subcommand)]
+	Cid(CidCmd),
 	/// Democracy-related commands
 	#[command(subcommand)]
 	Democracy(DemocracyCmd),
@@ -0,0 +1,209 @@
+//! Usage of a community currency, computed from events without an external indexer.
+//!
+//! Blocks are scanned one by one, so keep ranges reasonably small on public nodes. Transfers,
+//! issuance and fees paid in the community currency are bucketed into periods of a fixed number of
+//! blocks. Velocity is the transfer volume of a period relative to the issuance at its end.
+
+use crate::{
+	cli::Cli,
+	commands::{
+		encointer_core::get_community_issuance,
+		frame::{get_block_hash, get_block_number},
+	},
+	utils::get_chain_api,
+};
+use encointer_api_client_extension::{Api, BazaarApi, CommunitiesApi};
+use encointer_node_runtime::{AccountId, AssetBalance, AssetId, BlockNumber};
+use encointer_primitives::{
+	balances::{BalanceType, EncointerBalanceConverter},
+	communities::CommunityIdentifier,
+};
+use log::{info, warn};
+use parity_scale_codec::Decode;
+use serde::Serialize;
+use sp_runtime::traits::Convert;
+use std::collections::HashSet;
+use substrate_api_client::FetchEvents;
+
+const ENCOINTER_BALANCES: &str = "EncointerBalances";
+const ASSET_TX_PAYMENT: &str = "AssetTxPayment";
+
+#[derive(Default)]
+struct PeriodEvents {
+	/// (from, to, amount)
+	transfers: Vec<(AccountId, AccountId, BalanceType)>,
+	issued: BalanceType,
+	fees: BalanceType,
+	fee_payments: u64,
+	skipped_blocks: u32,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodAnalytics {
+	pub from_block: BlockNumber,
+	pub to_block: BlockNumber,
+	/// Blocks whose events could not be fetched. Their events are missing from all figures.
+	pub skipped_blocks: u32,
+	pub transfers: usize,
+	pub transfer_volume: f64,
+	pub median_transfer: Option<f64>,
+	pub active_senders: usize,
+	pub active_receivers: usize,
+	pub issued: f64,
+	pub fee_payments: u64,
+	pub fees_paid: f64,
+	/// Total issuance at `to_block`, demurrage applied.
+	pub issuance: f64,
+	pub velocity: Option<f64>,
+	/// Share of the transfer volume sent or received by bazaar businesses.
+	pub business_volume_share: Option<f64>,
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+	if values.is_empty() {
+		return None
+	}
+	values.sort_by(|a, b| a.total_cmp(b));
+	let mid = values.len() / 2;
+	Some(if values.len() % 2 == 0 { (values[mid - 1] + values[mid]) / 2.0 } else { values[mid] })
+}
+
+/// Collects the events of `cid` in blocks `from..=to`, fetching the events of every block once.
+async fn scan_period(
+	api: &Api,
+	cid: CommunityIdentifier,
+	from: BlockNumber,
+	to: BlockNumber,
+) -> PeriodEvents {
+	let mut found = PeriodEvents::default();
+	for block_number in from..=to {
+		let hash = get_block_hash(api, block_number).await;
+		let events = match api.fetch_events_from_block(hash).await {
+			Ok(events) => events,
+			Err(e) => {
+				warn!("skipping block {}, could not fetch its events: {:?}", block_number, e);
+				found.skipped_blocks += 1;
+				continue
+			},
+		};
+		for event in events.iter().flatten() {
+			let mut fields = event.field_bytes();
+			match (event.pallet_name(), event.variant_name()) {
+				(ENCOINTER_BALANCES, "Transferred") => {
+					match <(CommunityIdentifier, AccountId, AccountId, BalanceType)>::decode(
+						&mut fields,
+					) {
+						Ok((c, from, to, amount)) if c == cid =>
+							found.transfers.push((from, to, amount)),
+						Ok(_) => (),
+						Err(e) => warn!("could not decode Transferred: {:?}", e),
+					}
+				},
+				(ENCOINTER_BALANCES, "Issued") =>
+					match <(CommunityIdentifier, AccountId, BalanceType)>::decode(&mut fields) {
+						Ok((c, _, amount)) if c == cid => found.issued += amount,
+						Ok(_) => (),
+						Err(e) => warn!("could not decode Issued: {:?}", e),
+					},
+				(ASSET_TX_PAYMENT, "AssetTxFeePaid") =>
+					match <(AccountId, AssetBalance, AssetBalance, Option<AssetId>)>::decode(
+						&mut fields,
+					) {
+						Ok((_, actual_fee, tip, Some(c))) if c == cid => {
+							found.fees += EncointerBalanceConverter::convert(actual_fee + tip);
+							found.fee_payments += 1;
+						},
+						Ok(_) => (),
+						Err(e) => warn!("could not decode AssetTxFeePaid: {:?}", e),
+					},
+				_ => (),
+			}
+		}
+	}
+	found
+}
+
+pub async fn print_analytics(
+	cli: &Cli,
+	from_block: BlockNumber,
+	to_block: Option<BlockNumber>,
+	period: BlockNumber,
+) {
+	let api = get_chain_api(cli).await;
+	let cid = api
+		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
+		.await;
+	let to_block = match to_block {
+		Some(to) => to,
+		None => get_block_number(&api, cli.at_block()).await,
+	};
+	if from_block > to_block {
+		panic!("--from-block {from_block} is after --to-block {to_block}");
+	}
+	let period = period.max(1);
+
+	// businesses registered now, the bazaar keeps no history
+	let businesses: HashSet<AccountId> = api
+		.get_business_accounts(cid, None)
+		.await
+		.expect("could not read the business registry")
+		.into_iter()
+		.collect();
+	info!("{} bazaar businesses in {}", businesses.len(), cid);
+
+	let mut series = Vec::new();
+	let mut start = from_block;
+	while start <= to_block {
+		let end = start.saturating_add(period - 1).min(to_block);
+		info!("scanning blocks {}..={}", start, end);
+		let events = scan_period(&api, cid, start, end).await;
+
+		let amounts: Vec<f64> =
+			events.transfers.iter().map(|(_, _, a)| a.to_num::<f64>()).collect();
+		let transfer_volume: f64 = amounts.iter().sum();
+		let business_volume: f64 = events
+			.transfers
+			.iter()
+			.filter(|(from, to, _)| businesses.contains(from) || businesses.contains(to))
+			.map(|(_, _, a)| a.to_num::<f64>())
+			.sum();
+		let issuance =
+			get_community_issuance(&api, &cid.to_string(), Some(get_block_hash(&api, end).await))
+				.await
+				.to_num::<f64>();
+
+		series.push(PeriodAnalytics {
+			from_block: start,
+			to_block: end,
+			skipped_blocks: events.skipped_blocks,
+			transfers: events.transfers.len(),
+			transfer_volume,
+			median_transfer: median(amounts),
+			active_senders: events
+				.transfers
+				.iter()
+				.map(|(f, _, _)| f)
+				.collect::<HashSet<_>>()
+				.len(),
+			active_receivers: events
+				.transfers
+				.iter()
+				.map(|(_, t, _)| t)
+				.collect::<HashSet<_>>()
+				.len(),
+			issued: events.issued.to_num::<f64>(),
+			fee_payments: events.fee_payments,
+			fees_paid: events.fees.to_num::<f64>(),
+			issuance,
+			velocity: (issuance > 0.0).then(|| transfer_volume / issuance),
+			business_volume_share: (transfer_volume > 0.0)
+				.then(|| business_volume / transfer_volume),
+		});
+		start = end.saturating_add(1);
+		if end == BlockNumber::MAX {
+			break
+		}
+	}
+	println!("{}", serde_json::to_string_pretty(&series).unwrap());
+}
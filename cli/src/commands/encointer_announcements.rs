@@ -0,0 +1,263 @@
+//! Community announcements signed by the community's `AnnouncementSigner`.
+//!
+//! An announcement is plain JSON. It is valid if `signature` is a BIP340 schnorr signature by
+//! `signer` over `sha256(payload)` with
+//! `payload = SCALE((version: u8, communityIdentifier, title: String, body: String,
+//! locale: String, validFrom: u64, validUntil: u64, attachment: Option<String>))`
+//! and `signer` is the `announcement_signer` in the on-chain metadata of the community.
+
+use crate::{
+	cli::Cli, community_spec::parse_community_identifier, exit_code, utils::get_chain_api,
+};
+use chrono::{DateTime, Utc};
+use encointer_api_client_extension::CommunitiesApi;
+use encointer_node_runtime::Moment;
+use encointer_primitives::communities::AnnouncementSigner;
+use k256::schnorr::{Signature, SigningKey, VerifyingKey};
+use log::{error, info};
+use parity_scale_codec::Encode;
+use serde::{Deserialize, Serialize};
+use sp_core::hashing::sha2_256;
+
+const ANNOUNCEMENT_FORMAT: &str = "encointer-community-announcement";
+const ANNOUNCEMENT_VERSION: u8 = 1;
+/// Holds the hex encoded secret key if `--secret-key-file` is not given.
+const SECRET_KEY_ENV: &str = "ENCOINTER_ANNOUNCEMENT_SECRET_KEY";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+	pub format: String,
+	pub version: u8,
+	pub community_identifier: String,
+	pub title: String,
+	pub body: String,
+	pub locale: String,
+	/// Unix time in ms.
+	pub valid_from: Moment,
+	/// Unix time in ms.
+	pub valid_until: Moment,
+	/// IPFS cid of an attachment.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub attachment: Option<String>,
+	/// BIP340 x-only public key, hex encoded.
+	pub signer: String,
+	/// BIP340 signature over the payload, see module docs.
+	pub signature: String,
+}
+
+impl Announcement {
+	/// `None` if the community identifier is invalid.
+	fn payload_digest(&self) -> Option<[u8; 32]> {
+		let cid = parse_community_identifier(&self.community_identifier)?;
+		Some(sha2_256(
+			&(
+				self.version,
+				cid,
+				self.title.clone(),
+				self.body.clone(),
+				self.locale.clone(),
+				self.valid_from,
+				self.valid_until,
+				self.attachment.clone(),
+			)
+				.encode(),
+		))
+	}
+
+	/// Sets `signer` and `signature` for `signing_key`.
+	fn sign(&mut self, signing_key: &SigningKey) {
+		self.signer = format!("0x{}", hex::encode(signing_key.verifying_key().to_bytes()));
+		let digest = self.payload_digest().expect("cid must be valid");
+		// auxiliary randomness only hardens against side channels, see BIP340
+		let aux_rand = sha2_256(&[&digest[..], &now().to_le_bytes()].concat());
+		let signature = signing_key.sign_raw(&digest, &aux_rand).expect("signing failed");
+		self.signature = format!("0x{}", hex::encode(signature.to_bytes()));
+	}
+
+	/// Checks the signature against the contained signer. Works offline.
+	///
+	/// Returns false for anything malformed, the announcement is untrusted input.
+	pub fn verify_signature(&self) -> bool {
+		if self.format != ANNOUNCEMENT_FORMAT {
+			error!("unknown announcement format {}", self.format);
+			return false
+		}
+		if self.version != ANNOUNCEMENT_VERSION {
+			error!("unsupported announcement version {}", self.version);
+			return false
+		}
+		let Some(signer) = decode_hex(&self.signer)
+			.filter(|s| s.len() == 32)
+			.and_then(|s| VerifyingKey::from_bytes(&s).ok())
+		else {
+			return false
+		};
+		let Some(signature) =
+			decode_hex(&self.signature).and_then(|s| Signature::try_from(&s[..]).ok())
+		else {
+			return false
+		};
+		let Some(digest) = self.payload_digest() else { return false };
+		signer.verify_raw(&digest, &signature).is_ok()
+	}
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+	hex::decode(value.trim_start_matches("0x")).ok()
+}
+
+/// Reads the hex encoded signer key from `file`, or from `$ENCOINTER_ANNOUNCEMENT_SECRET_KEY`.
+fn read_signing_key(file: Option<&str>) -> SigningKey {
+	let secret = match file {
+		Some(path) => std::fs::read_to_string(path)
+			.unwrap_or_else(|e| panic!("could not read secret key file {path}: {e}")),
+		None => std::env::var(SECRET_KEY_ENV)
+			.unwrap_or_else(|_| panic!("please supply --secret-key-file or set {SECRET_KEY_ENV}")),
+	};
+	decode_hex(secret.trim())
+		.filter(|bytes| bytes.len() == 32)
+		.and_then(|bytes| SigningKey::from_bytes(&bytes).ok())
+		.expect("secret key must be a hex encoded 32 byte secp256k1 key")
+}
+
+fn parse_time(value: &str) -> Moment {
+	DateTime::parse_from_rfc3339(value)
+		.unwrap_or_else(|e| panic!("invalid time {value}, expected RFC 3339: {e}"))
+		.timestamp_millis() as Moment
+}
+
+fn now() -> Moment {
+	Utc::now().timestamp_millis() as Moment
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn sign_announcement(
+	cli: &Cli,
+	secret_key_file: Option<&str>,
+	title: &str,
+	body: &str,
+	locale: &str,
+	valid_from: Option<&str>,
+	valid_until: &str,
+	attachment: Option<&str>,
+	out: Option<&str>,
+) {
+	let cid = parse_community_identifier(cli.cid.as_deref().expect("please supply argument --cid"))
+		.expect("invalid community identifier");
+	let signing_key = read_signing_key(secret_key_file);
+
+	let mut announcement = Announcement {
+		format: ANNOUNCEMENT_FORMAT.into(),
+		version: ANNOUNCEMENT_VERSION,
+		community_identifier: cid.to_string(),
+		title: title.into(),
+		body: body.into(),
+		locale: locale.into(),
+		valid_from: valid_from.map_or_else(now, parse_time),
+		valid_until: parse_time(valid_until),
+		attachment: attachment.map(|a| a.to_string()),
+		signer: String::new(),
+		signature: String::new(),
+	};
+	if announcement.valid_until <= announcement.valid_from {
+		panic!("announcement must be valid until after it becomes valid");
+	}
+	announcement.sign(&signing_key);
+	info!("signed announcement for {} as {}", cid, announcement.signer);
+
+	let json = serde_json::to_string_pretty(&announcement).unwrap();
+	match out {
+		Some(path) => {
+			std::fs::write(path, json).expect("could not write announcement");
+			println!("{path}");
+		},
+		None => println!("{json}"),
+	}
+}
+
+pub async fn verify_announcement(cli: &Cli, file: &str, offline: bool) {
+	let announcement: Announcement =
+		serde_json::from_str(&std::fs::read_to_string(file).expect("could not read announcement"))
+			.unwrap_or_else(|e| {
+				error!("invalid announcement {file}: {e}");
+				std::process::exit(exit_code::INVALID_ANNOUNCEMENT);
+			});
+
+	let mut valid = announcement.verify_signature();
+	println!("community: {}", announcement.community_identifier);
+	println!("signer: {}", announcement.signer);
+	println!("signature valid: {valid}");
+
+	let current = (announcement.valid_from..announcement.valid_until).contains(&now());
+	println!("currently valid: {current}");
+	valid &= current;
+
+	if !offline {
+		let api = get_chain_api(cli).await;
+		let cid = api.verify_cid(&announcement.community_identifier, cli.at_block()).await;
+		let signer = decode_hex(&announcement.signer).unwrap_or_default();
+		let authorized = match api
+			.get_community_metadata(cid, cli.at_block())
+			.await
+			.and_then(|m| m.announcement_signer)
+		{
+			Some(AnnouncementSigner::Bip340(key)) => key.encode() == signer,
+			None => {
+				error!("community {} has no announcement signer", cid);
+				false
+			},
+		};
+		println!("signer authorized by community metadata: {authorized}");
+		valid &= authorized;
+	}
+
+	if !valid {
+		std::process::exit(exit_code::INVALID_ANNOUNCEMENT);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn signed_announcement() -> Announcement {
+		let mut announcement = Announcement {
+			format: ANNOUNCEMENT_FORMAT.into(),
+			version: ANNOUNCEMENT_VERSION,
+			community_identifier: "u0qj944rhWE".into(),
+			title: "Cycle 42".into(),
+			body: "The meetup in the park moves to the library.".into(),
+			locale: "en".into(),
+			valid_from: 1_700_000_000_000,
+			valid_until: 1_800_000_000_000,
+			attachment: None,
+			signer: String::new(),
+			signature: String::new(),
+		};
+		announcement.sign(&SigningKey::from_bytes(&[7u8; 32]).unwrap());
+		announcement
+	}
+
+	#[test]
+	fn signed_announcement_verifies_after_json_round_trip() {
+		let json = serde_json::to_string(&signed_announcement()).unwrap();
+		let announcement: Announcement = serde_json::from_str(&json).unwrap();
+		assert!(announcement.verify_signature());
+	}
+
+	#[test]
+	fn tampered_announcement_does_not_verify() {
+		let mut announcement = signed_announcement();
+		announcement.body = "The meetup is cancelled.".into();
+		assert!(!announcement.verify_signature());
+
+		let mut announcement = signed_announcement();
+		announcement.community_identifier = "u0qj9".into();
+		assert!(!announcement.verify_signature());
+
+		let mut announcement = signed_announcement();
+		announcement.signer = signed_announcement().signature;
+		assert!(!announcement.verify_signature());
+	}
+}
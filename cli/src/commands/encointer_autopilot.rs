@@ -0,0 +1,485 @@
+//! Ceremony autopilot: performs the ceremony duties of a set of keystore accounts.
+//!
+//! The autopilot follows `PhaseChangedTo` events. In Registering it claims the outstanding rewards
+//! of the previous ceremony and registers all accounts, in Attesting it attests the
+//! co-participants of every account's meetup. Completed work is persisted to a state file and
+//! double-checked against chain state, so the autopilot can be restarted at any time.
+
+use crate::{
+	cli::Cli,
+	commands::encointer_ceremonies::{get_reputation, prove_attendance},
+	utils::{
+		get_chain_api,
+		keys::{get_accountid_from_str, get_pair_from_str, KEYSTORE_PATH},
+	},
+};
+use ac_keystore::{KeystoreExt, LocalKeystore};
+use encointer_api_client_extension::{
+	set_api_extrisic_params_builder, Api, CeremoniesApi, CommunitiesApi, EncointerXt,
+	ParentchainExtrinsicSigner, SchedulerApi, ENCOINTER_CEREMONIES,
+};
+use encointer_node_runtime::{Hash, RuntimeEvent};
+use encointer_primitives::{
+	ceremonies::{CeremonyIndexType, MeetupIndexType, Reputation},
+	communities::CommunityIdentifier,
+	scheduler::CeremonyPhaseType,
+};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use sp_application_crypto::{sr25519, Ss58Codec};
+use sp_core::sr25519 as sr25519_core;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	path::{Path, PathBuf},
+};
+use substrate_api_client::{
+	ac_compose_macros::compose_extrinsic, SubmitAndWatch, SubscribeEvents, XtStatus,
+};
+
+/// Number of past ceremonies we keep in the state file.
+const STATE_HISTORY_DEPTH: CeremonyIndexType = 3;
+
+/// Persistent record of the work done by the autopilot.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AutopilotState {
+	cid: String,
+	ceremonies: BTreeMap<CeremonyIndexType, CeremonyProgress>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CeremonyProgress {
+	/// Accounts (SS58) whose registration is confirmed.
+	registered: BTreeSet<String>,
+	/// Accounts (SS58) whose attestations are confirmed.
+	attested: BTreeSet<String>,
+	/// Meetups of this ceremony for which rewards have been issued.
+	claimed_meetups: BTreeSet<MeetupIndexType>,
+}
+
+impl AutopilotState {
+	/// Loads the state, or starts from scratch if there is none. A corrupt state file is moved
+	/// aside, the work it recorded is recovered from chain state.
+	fn load(path: &Path, cid: CommunityIdentifier) -> Self {
+		let fresh = || Self { cid: cid.to_string(), ..Default::default() };
+		let state = match std::fs::read_to_string(path) {
+			Ok(s) => match serde_json::from_str::<Self>(&s) {
+				Ok(state) => state,
+				Err(e) => {
+					let corrupt = path.with_extension("corrupt");
+					error!(
+						"corrupt autopilot state file {:?}: {}. Moving it to {:?} and starting from scratch",
+						path, e, corrupt
+					);
+					std::fs::rename(path, &corrupt).expect("could not move corrupt state file");
+					fresh()
+				},
+			},
+			Err(_) => {
+				info!("no autopilot state found at {:?}, starting from scratch", path);
+				fresh()
+			},
+		};
+		if state.cid != cid.to_string() {
+			panic!("state file {path:?} belongs to community {}, not {cid}", state.cid);
+		}
+		state
+	}
+
+	/// Writes the state to a temporary file first, so a crash can't leave a truncated file.
+	fn save(&self, path: &Path) {
+		let tmp = path.with_extension("tmp");
+		std::fs::write(&tmp, serde_json::to_string_pretty(self).unwrap())
+			.expect("could not write autopilot state");
+		std::fs::rename(&tmp, path).expect("could not write autopilot state");
+	}
+
+	fn progress(&mut self, cindex: CeremonyIndexType) -> &mut CeremonyProgress {
+		self.ceremonies.entry(cindex).or_default()
+	}
+
+	fn prune(&mut self, current_cindex: CeremonyIndexType) {
+		self.ceremonies
+			.retain(|cindex, _| *cindex + STATE_HISTORY_DEPTH >= current_cindex);
+	}
+}
+
+/// Retries unfinished duties of a phase once per block, up to `max_retries` times.
+struct Retries {
+	max_retries: u32,
+	attempts: u32,
+	pending: bool,
+}
+
+impl Retries {
+	fn new(max_retries: u32) -> Self {
+		Self { max_retries, attempts: 0, pending: true }
+	}
+
+	/// Whether the duties should be run (again) now.
+	fn is_due(&self) -> bool {
+		self.pending && self.attempts <= self.max_retries
+	}
+
+	/// Records an attempt. Returns true if it failed and was the last one.
+	fn record(&mut self, done: bool) -> bool {
+		self.attempts += 1;
+		self.pending = !done;
+		self.pending && self.attempts > self.max_retries
+	}
+
+	/// Starts over for a new phase.
+	fn reset(&mut self) {
+		*self = Self::new(self.max_retries);
+	}
+}
+
+struct Autopilot {
+	api: Api,
+	cid: CommunityIdentifier,
+	accounts: Vec<String>,
+	state: AutopilotState,
+	state_path: PathBuf,
+	tx_payment_cid: Option<String>,
+}
+
+pub async fn run_autopilot(cli: &Cli, accounts_arg: &[String], state_file: &str, max_retries: u32) {
+	let api = get_chain_api(cli).await;
+	let cid = api
+		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
+		.await;
+
+	let accounts =
+		if accounts_arg.is_empty() { keystore_accounts() } else { accounts_arg.to_vec() };
+	assert!(
+		!accounts.is_empty(),
+		"no accounts to pilot. supply --accounts or add accounts to {KEYSTORE_PATH}"
+	);
+	info!("autopilot for {} accounts in community {}", accounts.len(), cid);
+
+	let state_path = PathBuf::from(state_file);
+	let state = AutopilotState::load(&state_path, cid);
+	let mut pilot = Autopilot {
+		api,
+		cid,
+		accounts,
+		state,
+		state_path,
+		tx_payment_cid: cli.tx_payment_cid.clone(),
+	};
+
+	// catch up with the current phase, we may have been (re)started in the middle of a cycle.
+	let mut phase = pilot.api.get_current_phase(None).await.unwrap();
+	let mut retries = Retries::new(max_retries);
+
+	let mut subscription = pilot.api.subscribe_events().await.unwrap();
+	loop {
+		// retry unfinished duties once per block until we run out of attempts
+		if retries.is_due() && retries.record(pilot.run_duties(phase).await) {
+			error!(
+				"giving up on duties for phase {:?} after {} attempts, will resume next phase",
+				phase, retries.attempts
+			);
+		}
+
+		let events = match subscription.next_events::<RuntimeEvent, Hash>().await {
+			Some(Ok(events)) => events,
+			Some(Err(e)) => {
+				error!("couldn't decode event record list: {:?}", e);
+				continue
+			},
+			None => {
+				warn!("event subscription ended, resubscribing");
+				subscription = pilot.api.subscribe_events().await.unwrap();
+				continue
+			},
+		};
+
+		let phase_change = events.iter().find_map(|evr| match &evr.event {
+			RuntimeEvent::EncointerScheduler(
+				pallet_encointer_scheduler::Event::PhaseChangedTo(new_phase),
+			) => Some(*new_phase),
+			_ => None,
+		});
+
+		if let Some(new_phase) = phase_change {
+			println!("Phase changed to: {new_phase:?}");
+			phase = new_phase;
+			retries.reset();
+		}
+	}
+}
+
+impl Autopilot {
+	/// Performs all duties of the phase. Returns true if nothing is left to do.
+	async fn run_duties(&mut self, phase: CeremonyPhaseType) -> bool {
+		let cindex = self.api.get_ceremony_index(None).await;
+		self.state.prune(cindex);
+		debug!("running duties for phase {:?} and cindex {}", phase, cindex);
+
+		let done = match phase {
+			CeremonyPhaseType::Registering => {
+				let claimed = self.claim_rewards(cindex - 1).await;
+				let registered = self.register(cindex).await;
+				claimed && registered
+			},
+			CeremonyPhaseType::Assigning => {
+				info!("nothing to do in phase Assigning");
+				true
+			},
+			CeremonyPhaseType::Attesting => self.attest(cindex).await,
+		};
+		self.state.save(&self.state_path);
+		done
+	}
+
+	async fn register(&mut self, cindex: CeremonyIndexType) -> bool {
+		let mut done = true;
+		for account in self.accounts.clone() {
+			if self.state.progress(cindex).registered.contains(&account) {
+				continue
+			}
+			let accountid = get_accountid_from_str(&account);
+			if self.api.get_registration(&(self.cid, cindex), &accountid, None).await.is_ok() {
+				debug!("{} is already registered for cindex {}", account, cindex);
+				self.state.progress(cindex).registered.insert(account);
+				continue
+			}
+
+			let proof =
+				match get_reputation(&self.api, &accountid, self.cid, cindex - 1, None).await {
+					Reputation::VerifiedUnlinked | Reputation::VerifiedLinked(_) =>
+						Some(prove_attendance(accountid, self.cid, cindex - 1, &account)),
+					_ => None,
+				};
+			let api = self.api_for(&account).await;
+			let xt: EncointerXt<_> = compose_extrinsic!(
+				api,
+				ENCOINTER_CEREMONIES,
+				"register_participant",
+				self.cid,
+				proof
+			)
+			.unwrap();
+			let result = submit(&api, xt).await;
+			match result {
+				Ok(()) => {
+					println!("registered {account} for cindex {cindex}");
+					self.state.progress(cindex).registered.insert(account);
+				},
+				Err(e) => {
+					warn!("registration of {} failed: {}", account, e);
+					done = false;
+				},
+			}
+		}
+		done
+	}
+
+	async fn attest(&mut self, cindex: CeremonyIndexType) -> bool {
+		let cc = (self.cid, cindex);
+		let mut done = true;
+		for account in self.accounts.clone() {
+			if self.state.progress(cindex).attested.contains(&account) {
+				continue
+			}
+			let accountid = get_accountid_from_str(&account);
+			if self.api.get_participant_attestation_index(cc, &accountid, None).await.is_some() {
+				debug!("{} has already attested for cindex {}", account, cindex);
+				self.state.progress(cindex).attested.insert(account);
+				continue
+			}
+			let meetup_index = match self.api.get_meetup_index(&cc, &accountid, None).await {
+				Ok(Some(m)) => m,
+				_ => {
+					info!("{} is not assigned to a meetup in cindex {}", account, cindex);
+					continue
+				},
+			};
+			let participants = match self.api.get_meetup_participants(&cc, meetup_index, None).await
+			{
+				Ok(p) => p,
+				Err(e) => {
+					warn!("could not fetch participants of meetup {}: {:?}", meetup_index, e);
+					done = false;
+					continue
+				},
+			};
+			let vote = participants.len() as u32;
+			let attestees: Vec<_> = participants.into_iter().filter(|p| p != &accountid).collect();
+
+			let api = self.api_for(&account).await;
+			let xt: EncointerXt<_> = compose_extrinsic!(
+				api,
+				ENCOINTER_CEREMONIES,
+				"attest_attendees",
+				self.cid,
+				vote,
+				attestees
+			)
+			.unwrap();
+			let result = submit(&api, xt).await;
+			match result {
+				Ok(()) => {
+					println!(
+						"{account} attested {} co-participants in meetup {meetup_index}",
+						vote - 1
+					);
+					self.state.progress(cindex).attested.insert(account);
+				},
+				Err(e) => {
+					warn!("attestation of {} failed: {}", account, e);
+					done = false;
+				},
+			}
+		}
+		done
+	}
+
+	/// Claims the rewards of all meetups of the piloted accounts which haven't been claimed yet.
+	async fn claim_rewards(&mut self, cindex: CeremonyIndexType) -> bool {
+		let cc = (self.cid, cindex);
+		let mut meetups: BTreeMap<MeetupIndexType, String> = BTreeMap::new();
+		for account in self.accounts.iter() {
+			let accountid = get_accountid_from_str(account);
+			if let Ok(Some(m)) = self.api.get_meetup_index(&cc, &accountid, None).await {
+				meetups.entry(m).or_insert_with(|| account.clone());
+			}
+		}
+
+		let mut done = true;
+		for (meetup_index, claimant) in meetups {
+			if self.state.progress(cindex).claimed_meetups.contains(&meetup_index) {
+				continue
+			}
+			if self.api.is_reward_issued(&cc, meetup_index, None).await.unwrap_or(false) {
+				debug!("rewards for meetup {} of cindex {} already issued", meetup_index, cindex);
+				self.state.progress(cindex).claimed_meetups.insert(meetup_index);
+				continue
+			}
+			let api = self.api_for(&claimant).await;
+			let xt: EncointerXt<_> = compose_extrinsic!(
+				api,
+				ENCOINTER_CEREMONIES,
+				"claim_rewards",
+				self.cid,
+				Some(meetup_index)
+			)
+			.unwrap();
+			let result = submit(&api, xt).await;
+			match result {
+				Ok(()) => {
+					println!("claimed rewards for meetup {meetup_index} of cindex {cindex}");
+					self.state.progress(cindex).claimed_meetups.insert(meetup_index);
+				},
+				Err(e) => {
+					warn!("claiming rewards for meetup {} failed: {}", meetup_index, e);
+					done = false;
+				},
+			}
+		}
+		done
+	}
+
+	async fn api_for(&self, account: &str) -> Api {
+		let mut api = self.api.clone();
+		let signer = sr25519_core::Pair::from(get_pair_from_str(account));
+		api.set_signer(ParentchainExtrinsicSigner::new(signer));
+		set_api_extrisic_params_builder(&mut api, self.tx_payment_cid.as_deref()).await;
+		api
+	}
+}
+
+/// Submits the extrinsic and waits for inclusion. Dispatch errors are reported as `Err`.
+async fn submit<C: parity_scale_codec::Encode>(
+	api: &Api,
+	xt: EncointerXt<C>,
+) -> Result<(), String> {
+	api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock)
+		.await
+		.map(|report| debug!("included in block {:?}", report.block_hash))
+		.map_err(|e| format!("{e:?}"))
+}
+
+fn keystore_accounts() -> Vec<String> {
+	let store = LocalKeystore::open(PathBuf::from(&KEYSTORE_PATH), None).unwrap();
+	let accounts = store
+		.public_keys::<sr25519::AppPublic>()
+		.unwrap()
+		.into_iter()
+		.map(|pubkey| pubkey.to_ss58check())
+		.collect();
+	drop(store);
+	accounts
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::str::FromStr;
+
+	fn cid() -> CommunityIdentifier {
+		CommunityIdentifier::from_str("u0qj944rhWE").unwrap()
+	}
+
+	fn state_path(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("encointer-autopilot-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		dir.join(format!("{name}.json"))
+	}
+
+	#[test]
+	fn state_survives_save_and_load() {
+		let path = state_path("roundtrip");
+		let mut state = AutopilotState::load(&path, cid());
+		assert!(state.ceremonies.is_empty());
+		state.progress(5).registered.insert("//Alice".into());
+		state.progress(5).claimed_meetups.insert(2);
+		state.save(&path);
+
+		let mut state = AutopilotState::load(&path, cid());
+		assert!(state.progress(5).registered.contains("//Alice"));
+		assert!(state.progress(5).claimed_meetups.contains(&2));
+		std::fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn corrupt_state_is_moved_aside() {
+		let path = state_path("corrupt");
+		std::fs::write(&path, "{\"cid\": \"u0qj9").unwrap();
+		let state = AutopilotState::load(&path, cid());
+		assert_eq!(state.cid, cid().to_string());
+		assert!(state.ceremonies.is_empty());
+		assert!(!path.exists());
+		let corrupt = path.with_extension("corrupt");
+		assert_eq!(std::fs::read_to_string(&corrupt).unwrap(), "{\"cid\": \"u0qj9");
+		std::fs::remove_file(corrupt).unwrap();
+	}
+
+	#[test]
+	fn prune_keeps_recent_ceremonies() {
+		let mut state = AutopilotState::default();
+		for cindex in 1..=6 {
+			state.progress(cindex);
+		}
+		state.prune(6);
+		assert_eq!(state.ceremonies.keys().copied().collect::<Vec<_>>(), vec![3, 4, 5, 6]);
+	}
+
+	#[test]
+	fn retries_give_up_after_max_retries() {
+		let mut retries = Retries::new(2);
+		assert!(retries.is_due());
+		assert!(!retries.record(false));
+		assert!(!retries.record(false));
+		assert!(retries.is_due());
+		assert!(retries.record(false));
+		assert!(!retries.is_due());
+
+		retries.reset();
+		assert!(retries.is_due());
+		assert!(!retries.record(true));
+		assert!(!retries.is_due());
+	}
+}
@@ -1,5 +1,6 @@
 use crate::{
 	cli::Cli,
+	commands::frame::{get_block_number, get_events_in_range},
 	exit_code,
 	utils::{
 		collective_propose_call, contains_sudo_pallet, ensure_payment, get_chain_api,
@@ -9,25 +10,31 @@ use crate::{
 	},
 };
 use encointer_api_client_extension::{
-	set_api_extrisic_params_builder, Api, ApiClientError, AttestationState, CeremoniesApi,
-	CommunitiesApi, EncointerXt, ParentchainExtrinsicSigner, SchedulerApi, ENCOINTER_CEREMONIES,
+	admission_capacity, attestation_threshold, judge_meetup, set_api_extrisic_params_builder, Api,
+	ApiClientError, AssignmentParameters, AttestationState, CapacityConstraint, CeremoniesApi,
+	CommunitiesApi, EncointerXt, ParentchainExtrinsicSigner, Registration, RegistrationType,
+	SchedulerApi, ENCOINTER_CEREMONIES,
+};
+use encointer_node_runtime::{
+	AccountId, BlockNumber, Hash, MeetupMinSize, MeetupNewbieLimitDivider, MeetupSizeTarget,
+	Moment, Signature, ONE_DAY,
 };
-use encointer_node_runtime::{AccountId, Hash, Moment, Signature, ONE_DAY};
 use encointer_primitives::{
 	ceremonies::{
 		AttestationIndexType, CeremonyIndexType, ClaimOfAttendance, CommunityCeremony,
 		CommunityReputation, MeetupIndexType, ParticipantIndexType, ProofOfAttendance, Reputation,
 	},
-	communities::CommunityIdentifier,
+	communities::{CommunityIdentifier, Degree, Location},
 	scheduler::CeremonyPhaseType,
 };
 use itertools::Itertools;
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use parity_scale_codec::{Decode, Encode};
+use serde::Deserialize;
 use sp_application_crypto::sr25519;
 use sp_core::{crypto::Ss58Codec, sr25519 as sr25519_core, Pair};
 use sp_keyring::Sr25519Keyring as AccountKeyring;
-use sp_runtime::MultiSignature;
+use sp_runtime::{DispatchError, ModuleError, MultiSignature};
 use std::collections::HashMap;
 use substrate_api_client::{
 	ac_compose_macros::{compose_call, compose_extrinsic, rpc_params},
@@ -506,16 +513,130 @@ pub async fn unregister_participant(
 	info!("Unregister Participant sent for {}. status: '{:?}'", arg_who, report.status);
 }
 
-pub async fn endorse(cli: &Cli, bootstrapper_str: &str, endorsees: &[String]) {
+pub async fn endorse(cli: &Cli, endorser_str: &str, endorsees: &[String]) {
 	let mut api = get_chain_api(cli).await;
 	let cid = api
 		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
 		.await;
-	endorse_newcomers(&mut api, cid, bootstrapper_str, endorsees, cli.tx_payment_cid.as_deref())
+	let endorser = get_accountid_from_str(endorser_str);
+	let remaining = match get_remaining_endorsement_tickets(&api, cid, &endorser).await.unwrap() {
+		Some((endorser_type, remaining)) => {
+			info!("{} endorses as {:?} with {} tickets left", endorser, endorser_type, remaining);
+			remaining
+		},
+		None => {
+			error!("{} is neither a bootstrapper nor a reputable of {}", endorser, cid);
+			std::process::exit(exit_code::INVALID_REPUTATION);
+		},
+	};
+	if (remaining as usize) < endorsees.len() {
+		error!(
+			"{} has {} endorsement tickets left, but {} endorsees were supplied",
+			endorser,
+			remaining,
+			endorsees.len()
+		);
+		std::process::exit(exit_code::NO_ENDORSEMENT_TICKETS);
+	}
+	endorse_newcomers(&mut api, cid, endorser_str, endorsees, cli.tx_payment_cid.as_deref())
 		.await
 		.unwrap();
 }
 
+pub async fn list_endorsements(
+	cli: &Cli,
+	ceremony_index_arg: Option<i32>,
+	from_block: Option<BlockNumber>,
+	to_block: Option<BlockNumber>,
+) {
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let cids = match cli.cid.as_deref() {
+		Some(cid) => vec![api.verify_cid(cid, maybe_at).await],
+		None => api.get_community_identifiers(maybe_at).await.expect("no communities found"),
+	};
+	let cindex = match ceremony_index_arg {
+		Some(ci) => into_effective_cindex(ci, api.get_ceremony_index(maybe_at).await),
+		None => get_endorsement_cindex(&api, maybe_at).await,
+	};
+	let (tickets_per_bootstrapper, tickets_per_reputable) =
+		get_endorsement_tickets_per_endorser(&api, maybe_at).await.unwrap();
+	let lifetime = api.get_reputation_lifetime(maybe_at).await.unwrap_or(5);
+
+	// endorsers are only known from events, so we can only attribute if we scan blocks
+	let endorsed_events: Vec<(BlockNumber, (CommunityIdentifier, AccountId, AccountId))> =
+		match from_block {
+			Some(from) => {
+				let to = match to_block {
+					Some(to) => to,
+					None => get_block_number(&api, maybe_at).await,
+				};
+				info!("scanning blocks {}..={} for endorsements", from, to);
+				get_events_in_range(&api, ENCOINTER_CEREMONIES, "EndorsedParticipant", from, to)
+					.await
+			},
+			None => vec![],
+		};
+
+	for cid in cids {
+		let endorsees = get_endorsees(&api, (cid, cindex), maybe_at).await;
+		let mut endorsed_by: HashMap<AccountId, Vec<AccountId>> = HashMap::new();
+		for (_, (event_cid, endorser, endorsee)) in endorsed_events.iter() {
+			if *event_cid == cid && endorsees.contains(endorsee) {
+				endorsed_by.entry(endorser.clone()).or_default().push(endorsee.clone());
+			}
+		}
+
+		let bootstrappers = get_bootstrappers(&api, cid, maybe_at).await.unwrap();
+		let reputables =
+			get_reputables_within_lifetime(&api, cid, cindex, lifetime, &bootstrappers, maybe_at)
+				.await;
+
+		println!(
+			"endorsements for cid {cid} and ceremony nr {cindex}: {} endorsees, {} bootstrappers, {} reputables",
+			endorsees.len(),
+			bootstrappers.len(),
+			reputables.len()
+		);
+		println!("endorser, type, used, remaining, endorsees");
+		for (endorser, endorser_type) in bootstrappers
+			.iter()
+			.map(|b| (b, EndorserType::Bootstrapper))
+			.chain(reputables.iter().map(|r| (r, EndorserType::Reputable)))
+		{
+			let (total, burned) = match endorser_type {
+				EndorserType::Bootstrapper => (
+					tickets_per_bootstrapper,
+					get_burned_bootstrapper_tickets(&api, cid, endorser, maybe_at).await.unwrap(),
+				),
+				EndorserType::Reputable => (
+					tickets_per_reputable,
+					get_burned_reputable_tickets(&api, (cid, cindex), endorser, maybe_at)
+						.await
+						.unwrap(),
+				),
+			};
+			let endorsed = endorsed_by
+				.get(endorser)
+				.map(|e| e.iter().map(|a| a.to_ss58check()).join(";"))
+				.unwrap_or_default();
+			println!(
+				"{}, {:?}, {}, {}, {}",
+				endorser.to_ss58check(),
+				endorser_type,
+				burned,
+				total.saturating_sub(burned),
+				endorsed
+			);
+		}
+		if from_block.is_none() {
+			for e in endorsees.iter() {
+				println!("endorsee (endorser unknown, use --from-block): {}", e.to_ss58check());
+			}
+		}
+	}
+}
+
 pub async fn bootstrappers_with_remaining_newbie_tickets(cli: &Cli) {
 	let api = get_chain_api(cli).await;
 	let cid = api
@@ -585,14 +706,56 @@ pub async fn attest_attendees(cli: &Cli, account: &str, attestees_str: &[String]
 	println!("Claims sent by {}. status: '{:?}'", who.public(), report.status);
 }
 
-pub async fn new_claim(cli: &Cli, arg_who: &str, n_participants: u32) {
+/// Actual position and time of a participant, as read from `--position-file`.
+#[derive(Deserialize)]
+struct ActualPosition {
+	lat: f64,
+	lon: f64,
+	/// Unix time in ms. Defaults to now.
+	time: Option<Moment>,
+}
+
+fn read_position_file(file: &str) -> Result<ActualPosition, String> {
+	let content = std::fs::read_to_string(file).map_err(|e| e.to_string())?;
+	serde_json::from_str(&content)
+		.map_err(|e| format!("{e}. Expected {{\"lat\": .., \"lon\": .., \"time\": ..}}"))
+}
+
+pub async fn new_claim(
+	cli: &Cli,
+	arg_who: &str,
+	n_participants: u32,
+	lat_lon: Option<(f64, f64)>,
+	time: Option<Moment>,
+	position_file: Option<&str>,
+	force: bool,
+) {
 	let api = get_chain_api(cli).await;
 	let cid = api
 		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
 		.await;
 	let claimant = get_pair_from_str(arg_who);
 
-	let claim = new_claim_for(&api, &claimant.into(), cid, n_participants).await;
+	// clap ensures that a position file and --lat/--lon/--time are mutually exclusive
+	let position = match position_file {
+		Some(file) => {
+			let p = read_position_file(file).unwrap_or_else(|e| {
+				error!("invalid position file {file}: {e}");
+				std::process::exit(exit_code::INVALID_POSITION);
+			});
+			Some((p.lat, p.lon, p.time))
+		},
+		None => lat_lon.map(|(lat, lon)| (lat, lon, time)),
+	};
+	let actual = position.map(|(lat, lon, time)| {
+		let now = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.unwrap()
+			.as_millis() as Moment;
+		(Location { lat: Degree::from_num(lat), lon: Degree::from_num(lon) }, time.unwrap_or(now))
+	});
+
+	let claim = new_claim_for(&api, &claimant.into(), cid, n_participants, actual, force).await;
 
 	println!("{}", hex::encode(claim));
 }
@@ -602,6 +765,7 @@ pub async fn claim_reward(
 	signer_arg: Option<&str>,
 	meetup_index_arg: Option<u64>,
 	all: bool,
+	lookback: CeremonyIndexType,
 ) {
 	let api = get_chain_api(cli).await;
 	let cid = api
@@ -620,30 +784,7 @@ pub async fn claim_reward(
 	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
 
 	if all {
-		let mut cindex = api.get_ceremony_index(None).await;
-		if api.get_current_phase(None).await.unwrap() == CeremonyPhaseType::Registering {
-			cindex -= 1;
-		}
-		let meetup_count = api
-			.get_storage_map("EncointerCeremonies", "MeetupCount", (cid, cindex), None)
-			.await
-			.unwrap()
-			.unwrap_or(0u64);
-		let calls: Vec<_> = (1u64..=meetup_count)
-			.map(|idx| {
-				compose_call!(
-					api.metadata(),
-					ENCOINTER_CEREMONIES,
-					"claim_rewards",
-					cid,
-					Option::<MeetupIndexType>::Some(idx)
-				)
-				.unwrap()
-			})
-			.collect();
-		let batch_call = compose_call!(api.metadata(), "Utility", "batch", calls).unwrap();
-		send_and_wait_for_in_block(&api, xt(&api, batch_call).await, tx_payment_cid_arg).await;
-		println!("Claiming reward for all meetup indexes. xt-status: 'ready'");
+		claim_outstanding_rewards(&api, cid, lookback, tx_payment_cid_arg).await;
 	} else {
 		let meetup_index = meetup_index_arg;
 		let xt: EncointerXt<_> =
@@ -665,10 +806,363 @@ pub async fn claim_reward(
 	}
 }
 
+/// Claims the rewards of all meetups whose rewards have not been issued yet and which can be
+/// claimed now, and reports the outcome per meetup.
+///
+/// The chain only accepts claims for the last ceremony (or the current one in the attesting
+/// phase, if all participants have attested already). Unclaimed meetups of the `lookback`
+/// ceremonies before are only reported.
+async fn claim_outstanding_rewards(
+	api: &Api,
+	cid: CommunityIdentifier,
+	lookback: CeremonyIndexType,
+	tx_payment_cid_arg: Option<&str>,
+) {
+	let phase = api.get_current_phase(None).await.unwrap();
+	let cindex = match phase {
+		CeremonyPhaseType::Registering => api.get_ceremony_index(None).await - 1,
+		CeremonyPhaseType::Attesting => api.get_ceremony_index(None).await,
+		CeremonyPhaseType::Assigning => {
+			error!("rewards can't be claimed in the assigning phase");
+			std::process::exit(exit_code::WRONG_PHASE);
+		},
+	};
+
+	for past in cindex.saturating_sub(lookback).max(1)..cindex {
+		let cc = (cid, past);
+		for idx in 1..=api.get_meetup_count(&cc, None).await.unwrap_or(0) {
+			if !api.is_reward_issued(&cc, idx, None).await.unwrap() {
+				println!("cindex {past}, meetup {idx}: expired, claim period is over");
+			}
+		}
+	}
+
+	let cc = (cid, cindex);
+	let mut outstanding = Vec::new();
+	for idx in 1..=api.get_meetup_count(&cc, None).await.unwrap_or(0) {
+		if api.is_reward_issued(&cc, idx, None).await.unwrap() {
+			debug!("cindex {cindex}, meetup {idx}: rewards already issued");
+			continue
+		}
+		let participants = api.get_meetup_participants(&cc, idx, None).await.unwrap_or_default();
+		let mut attested = 0;
+		for p in participants.iter() {
+			if api.get_participant_attestation_index(cc, p, None).await.is_some() {
+				attested += 1;
+			}
+		}
+		if attested == 0 {
+			println!("cindex {cindex}, meetup {idx}: skipped, nobody attested");
+		} else if phase == CeremonyPhaseType::Attesting && attested < participants.len() {
+			println!(
+				"cindex {cindex}, meetup {idx}: skipped, only {attested} of {} participants attested. Early rewards are not possible",
+				participants.len()
+			);
+		} else {
+			outstanding.push(idx);
+		}
+	}
+	if outstanding.is_empty() {
+		println!("no outstanding rewards to claim for cindex {cindex}");
+		return
+	}
+
+	info!("claiming rewards for meetups {:?} of cindex {}", outstanding, cindex);
+	let calls: Vec<_> = outstanding
+		.iter()
+		.map(|idx| {
+			compose_call!(
+				api.metadata(),
+				ENCOINTER_CEREMONIES,
+				"claim_rewards",
+				cid,
+				Option::<MeetupIndexType>::Some(*idx)
+			)
+			.unwrap()
+		})
+		.collect();
+	// unlike `batch`, `force_batch` doesn't stop at the first failing claim
+	let batch_call = compose_call!(api.metadata(), "Utility", "force_batch", calls).unwrap();
+	let xt = xt(api, batch_call).await;
+	ensure_payment(api, &xt.encode().into(), tx_payment_cid_arg).await;
+	let report = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await.unwrap();
+
+	// the utility pallet emits one `ItemCompleted` or `ItemFailed` per call, in order
+	let item_results: Vec<Result<(), String>> = report
+		.events
+		.unwrap_or_default()
+		.iter()
+		.filter(|e| e.pallet_name() == "Utility")
+		.filter_map(|e| match e.variant_name() {
+			"ItemCompleted" => Some(Ok(())),
+			"ItemFailed" => Some(Err(describe_dispatch_error(api, &mut e.field_bytes()))),
+			_ => None,
+		})
+		.collect();
+
+	for (idx, result) in outstanding.iter().zip(item_results.iter()) {
+		match result {
+			Ok(()) => {
+				let attendees = count_verified_attendees(api, (cid, cindex), *idx).await;
+				if attendees > 0 {
+					println!("cindex {cindex}, meetup {idx}: paid out to {attendees} attendees");
+				} else {
+					println!(
+						"cindex {cindex}, meetup {idx}: no rewards issued, meetup could not be validated"
+					);
+				}
+			},
+			Err(reason) => println!("cindex {cindex}, meetup {idx}: failed, {reason}"),
+		}
+	}
+}
+
+async fn count_verified_attendees(
+	api: &Api,
+	community_ceremony: CommunityCeremony,
+	meetup_index: MeetupIndexType,
+) -> usize {
+	let participants = api
+		.get_meetup_participants(&community_ceremony, meetup_index, None)
+		.await
+		.unwrap_or_default();
+	let mut attendees = 0;
+	for p in participants.iter() {
+		let rep = get_reputation(api, p, community_ceremony.0, community_ceremony.1, None).await;
+		if matches!(rep, Reputation::VerifiedUnlinked | Reputation::VerifiedLinked(_)) {
+			attendees += 1;
+		}
+	}
+	attendees
+}
+
+/// Resolves the pallet error name of a dispatch error, if possible.
+fn describe_dispatch_error(api: &Api, field_bytes: &mut &[u8]) -> String {
+	match DispatchError::decode(field_bytes) {
+		Ok(DispatchError::Module(ModuleError { index, error, .. })) => api
+			.metadata()
+			.pallet_by_index(index)
+			.and_then(|pallet| {
+				pallet
+					.error_variant_by_index(error[0])
+					.map(|variant| format!("{}::{}", pallet.name(), variant.name))
+			})
+			.unwrap_or_else(|| format!("module error {index}:{}", error[0])),
+		Ok(e) => format!("{e:?}"),
+		Err(_) => "unknown error".into(),
+	}
+}
+
+pub async fn print_admission_capacity(cli: &Cli, reputables_arg: Option<u64>) {
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let cid = api
+		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), maybe_at)
+		.await;
+	let cindex = get_endorsement_cindex(&api, maybe_at).await;
+	let lifetime = api.get_reputation_lifetime(maybe_at).await.unwrap_or(5);
+	let bootstrappers = get_bootstrappers(&api, cid, maybe_at).await.unwrap();
+	// everybody with reputation in the lifetime window may register as reputable
+	let reputables = match reputables_arg {
+		Some(r) => r,
+		None =>
+			get_reputables_within_lifetime(&api, cid, cindex, lifetime, &bootstrappers, maybe_at)
+				.await
+				.len() as u64,
+	};
+	let constant = |name: &'static str, default: u64| {
+		let api = &api;
+		async move { api.get_constant::<u64>(ENCOINTER_CEREMONIES, name).await.unwrap_or(default) }
+	};
+	let params = AssignmentParameters {
+		meetup_size_target: constant("MeetupSizeTarget", MeetupSizeTarget::get()).await,
+		meetup_min_size: constant("MeetupMinSize", MeetupMinSize::get()).await,
+		meetup_newbie_limit_divider: constant(
+			"MeetupNewbieLimitDivider",
+			MeetupNewbieLimitDivider::get(),
+		)
+		.await,
+		locations: api.get_locations(cid).await.unwrap().len() as u64,
+		bootstrappers: bootstrappers.len() as u64,
+		reputables,
+	};
+	debug!("assignment parameters: {:?}", params);
+	let capacity = admission_capacity(params);
+
+	println!("admission capacity of {cid} for ceremony {cindex}");
+	println!(
+		"locations: {}, bootstrappers: {}, reputables: {}",
+		params.locations, params.bootstrappers, params.reputables
+	);
+	println!("max meetups: {}", capacity.max_meetups);
+	println!("max newbies (without endorsees): {}", capacity.max_newbies);
+	println!("max endorsees (without newbies): {}", capacity.max_endorsees);
+	println!("resulting meetups: {}", capacity.resulting_meetups);
+	match capacity.binding_constraint {
+		CapacityConstraint::Locations => println!(
+			"binding constraint: locations. There are more experienced participants than locations"
+		),
+		CapacityConstraint::Reputables => println!(
+			"binding constraint: reputables. Every meetup needs an experienced participant"
+		),
+		CapacityConstraint::NewbieLimit =>
+			println!("binding constraint: newbie limit. Free seats are left for endorsees only"),
+	}
+	println!(
+		"newbie limit: one newbie per {} experienced participants",
+		params.meetup_newbie_limit_divider
+	);
+	if capacity.suggested_additional_locations > 0 {
+		println!(
+			"add {} locations to admit the maximum number of newbies",
+			capacity.suggested_additional_locations
+		);
+	}
+	if !capacity.min_size_reached {
+		warn!("not all meetups will reach the minimum size of {}", params.meetup_min_size);
+	}
+}
+
+/// Replays the meetup validation for `account` and prints which rule decided its reputation.
+pub async fn explain_reputation(cli: &Cli, account: &str, ceremony_index_arg: Option<i32>) {
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let cid = api
+		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), maybe_at)
+		.await;
+	let current_cindex = api.get_ceremony_index(maybe_at).await;
+	let cindex =
+		ceremony_index_arg.map_or(current_cindex, |ci| into_effective_cindex(ci, current_cindex));
+	let cc = (cid, cindex);
+	let who = get_accountid_from_str(account);
+
+	println!("explaining reputation of {who} in {cid} for ceremony {cindex}");
+	let registration = match api.get_registration(&cc, &who, maybe_at).await {
+		Ok(r) => r,
+		Err(_) => {
+			println!("not registered (or the registry has already been purged)");
+			return
+		},
+	};
+	println!("registered as {:?}", registration.registration_type);
+
+	let Some(meetup_index) = api.get_meetup_index(&cc, &who, maybe_at).await.unwrap() else {
+		explain_unassigned(&api, &cc, &registration, maybe_at).await;
+		return
+	};
+	let participants = api.get_meetup_participants(&cc, meetup_index, maybe_at).await.unwrap();
+	let mut votes = Vec::with_capacity(participants.len());
+	let mut attestations = Vec::with_capacity(participants.len());
+	for p in participants.iter() {
+		votes.push(
+			api.get_meetup_participant_count_vote(cc, p.clone(), maybe_at)
+				.await
+				.unwrap_or(0),
+		);
+		let attested = match api.get_participant_attestation_index(cc, p, maybe_at).await {
+			Some(w) => api.get_attestees(cc, w, maybe_at).await.unwrap_or_default(),
+			None => vec![],
+		};
+		attestations.push(
+			attested
+				.iter()
+				.filter_map(|a| participants.iter().position(|q| q == a))
+				.collect::<Vec<usize>>(),
+		);
+	}
+
+	println!("meetup {meetup_index} with {} participants", participants.len());
+	println!("participant, registration, vote, attested");
+	for (i, p) in participants.iter().enumerate() {
+		let registration_type = api
+			.get_registration(&cc, p, maybe_at)
+			.await
+			.map(|r| format!("{:?}", r.registration_type))
+			.unwrap_or_default();
+		println!(
+			"{}, {}, {}, {:?}",
+			p.to_ss58check(),
+			registration_type,
+			votes[i],
+			attestations[i]
+		);
+	}
+
+	let own_index = participants.iter().position(|p| *p == who).unwrap();
+	match judge_meetup(&votes, &attestations) {
+		Err(e) => println!("meetup invalid: {e:?}. Nobody in this meetup gets reputation"),
+		Ok(judgement) => {
+			if let Some(majority_vote) = judgement.legit.first().map(|i| votes[*i]) {
+				println!(
+					"majority vote: {majority_vote}, attestation threshold: {}",
+					attestation_threshold(judgement.legit.len())
+				);
+			}
+			match judgement.excluded.iter().find(|e| e.index == own_index) {
+				Some(e) => println!("excluded: {:?}", e.reason),
+				None => println!("legit: all rules passed"),
+			}
+			let phase = api.get_current_phase(maybe_at).await.unwrap();
+			if cindex == current_cindex && phase == CeremonyPhaseType::Attesting {
+				println!(
+					"early rewards possible: {} (claiming during the attesting phase requires a unanimous, fully attested meetup)",
+					judgement.early_rewards_possible
+				);
+			}
+		},
+	}
+
+	if api.is_reward_issued(&cc, meetup_index, maybe_at).await.unwrap() {
+		println!("rewards for this meetup have been issued");
+	} else {
+		let phase = api.get_current_phase(maybe_at).await.unwrap();
+		let claimable = match phase {
+			CeremonyPhaseType::Registering => current_cindex.saturating_sub(1),
+			_ => current_cindex,
+		};
+		if cindex < claimable || (cindex == claimable && phase == CeremonyPhaseType::Assigning) {
+			println!("rewards have not been claimed in time and can no longer be issued");
+		} else {
+			println!("rewards have not been claimed yet");
+		}
+	}
+	println!("reputation: {:?}", get_reputation(&api, &who, cid, cindex, maybe_at).await);
+}
+
+/// Explains why a registered participant was not assigned to a meetup, following the admission
+/// order of the pallet: bootstrappers and reputables first, then endorsees, then newbies up to the
+/// newbie limit.
+async fn explain_unassigned(
+	api: &Api,
+	cc: &CommunityCeremony,
+	registration: &Registration,
+	maybe_at: Option<Hash>,
+) {
+	let meetup_count = api.get_meetup_count(cc, maybe_at).await.unwrap_or(0);
+	if meetup_count == 0 {
+		println!("not assigned to a meetup: no meetups have been assigned for this ceremony");
+		return
+	}
+	let assigned = api.get_assignment_counts(cc, maybe_at).await.unwrap();
+	let (admitted, rule) = match registration.registration_type {
+		RegistrationType::Bootstrapper => (assigned.bootstrappers, "meetup locations"),
+		RegistrationType::Reputable => (assigned.reputables, "meetup locations"),
+		RegistrationType::Endorsee => (assigned.endorsees, "seats left after bootstrappers and reputables"),
+		RegistrationType::Newbie => (
+			assigned.newbies,
+			"the newbie limit (one per MeetupNewbieLimitDivider experienced participants) and seats left",
+		),
+	};
+	println!(
+		"not assigned to a meetup: registered as {:?} number {} but only {} were admitted, limited by {}",
+		registration.registration_type, registration.index, admitted, rule
+	);
+}
+
 pub async fn reputation(cli: &Cli, account: &str) {
 	let api = get_chain_api(cli).await;
 	let account_id = get_accountid_from_str(account);
-	if let Some(reputation) = get_reputation_history(&api, &account_id).await {
+	if let Some(reputation) = get_reputation_history(&api, &account_id, cli.at_block()).await {
 		for rep in reputation.iter() {
 			println!("{}, {}, {:?}", rep.0, rep.1.community_identifier, rep.1.reputation);
 		}
@@ -753,7 +1247,7 @@ pub async fn purge_community_ceremony(cli: &Cli, from_cindex_arg: i32, to_cindex
 	info!("[+] Transaction got included. Block Hash: {:?}\n", tx_report.block_hash.unwrap());
 }
 
-fn prove_attendance(
+pub fn prove_attendance(
 	prover: AccountId,
 	cid: CommunityIdentifier,
 	cindex: CeremonyIndexType,
@@ -775,7 +1269,7 @@ fn prove_attendance(
 	}
 }
 
-async fn get_reputation(
+pub async fn get_reputation(
 	api: &Api,
 	prover: &AccountId,
 	cid: CommunityIdentifier,
@@ -794,17 +1288,18 @@ async fn get_reputation(
 	.unwrap_or(Reputation::Unverified)
 }
 
-async fn get_reputation_history(
+pub async fn get_reputation_history(
 	api: &Api,
 	account_id: &AccountId,
+	maybe_at: Option<Hash>,
 ) -> Option<Vec<(CeremonyIndexType, CommunityReputation)>> {
 	api.client()
-		.request("encointer_getReputations", rpc_params![account_id])
+		.request("encointer_getReputations", rpc_params![account_id, maybe_at])
 		.await
 		.expect("Could not query reputation history...")
 }
 
-async fn get_attendees_for_community_ceremony(
+pub async fn get_attendees_for_community_ceremony(
 	api: &Api,
 	community_ceremony: CommunityCeremony,
 	maybe_at: Option<Hash>,
@@ -852,6 +1347,8 @@ async fn new_claim_for(
 	claimant: &sr25519::Pair,
 	cid: CommunityIdentifier,
 	n_participants: u32,
+	actual: Option<(Location, Moment)>,
+	force: bool,
 ) -> Vec<u8> {
 	let cindex = api.get_ceremony_index(None).await;
 	let mindex = api
@@ -860,9 +1357,45 @@ async fn new_claim_for(
 		.unwrap()
 		.expect("participant must be assigned to meetup to generate a claim");
 
-	// implicitly assume that participant meet at the right place at the right time
-	let mloc = api.get_meetup_location(&(cid, cindex), mindex, None).await.unwrap().unwrap();
-	let mtime = api.get_meetup_time(mloc, ONE_DAY, None).await.unwrap();
+	let (mloc, mtime) = match actual {
+		Some((location, time)) => {
+			let plausibility = api
+				.check_claim_plausibility(&(cid, cindex), mindex, location, time, None)
+				.await
+				.unwrap();
+			debug!("claim plausibility: {:?}", plausibility);
+			if !plausibility.is_location_plausible() {
+				warn!(
+					"claimed location is {} m away from the meetup location (lat: {} lon: {}), tolerance is {} m",
+					plausibility.distance_m,
+					plausibility.meetup_location.lat,
+					plausibility.meetup_location.lon,
+					plausibility.location_tolerance_m
+				);
+			}
+			if !plausibility.is_time_plausible() {
+				warn!(
+					"claimed time deviates {} s from the meetup time {}, tolerance is {} s. Is your clock correct?",
+					plausibility.time_deviation_ms / 1000,
+					plausibility.meetup_time,
+					plausibility.time_tolerance_ms / 1000
+				);
+			}
+			if !plausibility.is_plausible() && !force {
+				error!(
+					"refusing to sign a claim the chain would discard. Use --force to sign anyway"
+				);
+				std::process::exit(exit_code::IMPLAUSIBLE_CLAIM);
+			}
+			(location, time)
+		},
+		None => {
+			// implicitly assume that participant meet at the right place at the right time
+			let mloc =
+				api.get_meetup_location(&(cid, cindex), mindex, None).await.unwrap().unwrap();
+			(mloc, api.get_meetup_time(mloc, ONE_DAY, None).await.unwrap())
+		},
+	};
 
 	info!(
 		"creating claim for {} at loc {} (lat: {} lon: {}) at time {}, cindex {}",
@@ -933,41 +1466,186 @@ async fn get_bootstrappers_with_remaining_newbie_tickets(
 	api: &Api,
 	cid: CommunityIdentifier,
 ) -> Result<Vec<BootstrapperWithTickets>, ApiClientError> {
-	let total_newbie_tickets: u8 = api
-		.get_storage("EncointerCeremonies", "EndorsementTicketsPerBootstrapper", None)
-		.await
-		.unwrap()
-		.unwrap();
-
-	// prepare closure to make below call more readable.
-	let ticket_query = |bs| async move {
-		let remaining_tickets = total_newbie_tickets -
-			api.get_storage_double_map(
-				"EncointerCeremonies",
-				"BurnedBootstrapperNewbieTickets",
-				cid,
-				bs,
-				None,
-			)
-			.await?
-			.unwrap_or(0u8);
-
-		Ok::<_, ApiClientError>(remaining_tickets)
-	};
+	let (total_newbie_tickets, _) = get_endorsement_tickets_per_endorser(api, None).await?;
 
-	let bootstrappers: Vec<AccountId> = api
-		.get_storage_map("EncointerCommunities", "Bootstrappers", cid, None)
-		.await?
-		.expect("No bootstrappers found, does the community exist?");
+	let bootstrappers = get_bootstrappers(api, cid, None).await?;
 
 	let mut bs_with_tickets: Vec<BootstrapperWithTickets> = Vec::with_capacity(bootstrappers.len());
 
 	for bs in bootstrappers.into_iter() {
+		let burned = get_burned_bootstrapper_tickets(api, cid, &bs, None).await?;
 		bs_with_tickets.push(BootstrapperWithTickets {
-			bootstrapper: bs.clone(),
-			remaining_newbie_tickets: ticket_query(bs).await?,
+			bootstrapper: bs,
+			remaining_newbie_tickets: total_newbie_tickets.saturating_sub(burned),
 		});
 	}
 
 	Ok(bs_with_tickets)
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EndorserType {
+	Bootstrapper,
+	Reputable,
+}
+
+/// The ceremony index newcomers are currently endorsed for.
+///
+/// Outside the registering phase, endorsements count for the next ceremony.
+async fn get_endorsement_cindex(api: &Api, maybe_at: Option<Hash>) -> CeremonyIndexType {
+	let cindex = api.get_ceremony_index(maybe_at).await;
+	match api.get_current_phase(maybe_at).await.unwrap() {
+		CeremonyPhaseType::Registering => cindex,
+		_ => cindex + 1,
+	}
+}
+
+/// Returns (tickets per bootstrapper, tickets per reputable).
+async fn get_endorsement_tickets_per_endorser(
+	api: &Api,
+	maybe_at: Option<Hash>,
+) -> Result<(u8, u8), ApiClientError> {
+	let per_bootstrapper = api
+		.get_storage(ENCOINTER_CEREMONIES, "EndorsementTicketsPerBootstrapper", maybe_at)
+		.await?
+		.unwrap_or(0u8);
+	let per_reputable = api
+		.get_storage(ENCOINTER_CEREMONIES, "EndorsementTicketsPerReputable", maybe_at)
+		.await?
+		.unwrap_or(0u8);
+	Ok((per_bootstrapper, per_reputable))
+}
+
+pub async fn get_bootstrappers(
+	api: &Api,
+	cid: CommunityIdentifier,
+	maybe_at: Option<Hash>,
+) -> Result<Vec<AccountId>, ApiClientError> {
+	Ok(api
+		.get_storage_map("EncointerCommunities", "Bootstrappers", cid, maybe_at)
+		.await?
+		.expect("No bootstrappers found, does the community exist?"))
+}
+
+/// Bootstrapper tickets are burned per community, not per ceremony.
+async fn get_burned_bootstrapper_tickets(
+	api: &Api,
+	cid: CommunityIdentifier,
+	bootstrapper: &AccountId,
+	maybe_at: Option<Hash>,
+) -> Result<u8, ApiClientError> {
+	Ok(api
+		.get_storage_double_map(
+			ENCOINTER_CEREMONIES,
+			"BurnedBootstrapperNewbieTickets",
+			cid,
+			bootstrapper,
+			maybe_at,
+		)
+		.await?
+		.unwrap_or(0u8))
+}
+
+async fn get_burned_reputable_tickets(
+	api: &Api,
+	community_ceremony: CommunityCeremony,
+	reputable: &AccountId,
+	maybe_at: Option<Hash>,
+) -> Result<u8, ApiClientError> {
+	Ok(api
+		.get_storage_double_map(
+			ENCOINTER_CEREMONIES,
+			"BurnedReputableNewbieTickets",
+			community_ceremony,
+			reputable,
+			maybe_at,
+		)
+		.await?
+		.unwrap_or(0u8))
+}
+
+/// Accounts with verified reputation in `[cindex - lifetime, cindex]` which are not bootstrappers.
+async fn get_reputables_within_lifetime(
+	api: &Api,
+	cid: CommunityIdentifier,
+	cindex: CeremonyIndexType,
+	lifetime: CeremonyIndexType,
+	bootstrappers: &[AccountId],
+	maybe_at: Option<Hash>,
+) -> Vec<AccountId> {
+	let mut reputables = Vec::new();
+	for c in cindex.saturating_sub(lifetime)..=cindex {
+		let (attendees, _) = get_attendees_for_community_ceremony(api, (cid, c), maybe_at).await;
+		reputables.extend(attendees.into_iter().filter(|a| !bootstrappers.contains(a)));
+	}
+	reputables.into_iter().unique().collect()
+}
+
+/// Returns the endorser type and its remaining tickets, or `None` if `endorser` may not endorse.
+///
+/// Mirrors the pallet: bootstrappers use their bootstrapper tickets, everybody else needs
+/// reputation within the reputation lifetime.
+async fn get_remaining_endorsement_tickets(
+	api: &Api,
+	cid: CommunityIdentifier,
+	endorser: &AccountId,
+) -> Result<Option<(EndorserType, u8)>, ApiClientError> {
+	let (per_bootstrapper, per_reputable) = get_endorsement_tickets_per_endorser(api, None).await?;
+
+	if get_bootstrappers(api, cid, None).await?.contains(endorser) {
+		let burned = get_burned_bootstrapper_tickets(api, cid, endorser, None).await?;
+		return Ok(Some((EndorserType::Bootstrapper, per_bootstrapper.saturating_sub(burned))))
+	}
+
+	let current_cindex = api.get_ceremony_index(None).await;
+	let lifetime = api.get_reputation_lifetime(None).await?;
+	let has_reputation = get_reputation_history(api, endorser, None)
+		.await
+		.unwrap_or_default()
+		.iter()
+		.any(|(c, rep)| {
+			rep.community_identifier == cid &&
+				*c >= current_cindex.saturating_sub(lifetime) &&
+				*c <= current_cindex &&
+				matches!(
+					rep.reputation,
+					Reputation::VerifiedUnlinked | Reputation::VerifiedLinked(_)
+				)
+		});
+	if !has_reputation {
+		return Ok(None)
+	}
+	let cindex = get_endorsement_cindex(api, None).await;
+	let burned = get_burned_reputable_tickets(api, (cid, cindex), endorser, None).await?;
+	Ok(Some((EndorserType::Reputable, per_reputable.saturating_sub(burned))))
+}
+
+async fn get_endorsees(
+	api: &Api,
+	community_ceremony: CommunityCeremony,
+	maybe_at: Option<Hash>,
+) -> Vec<AccountId> {
+	let key_prefix = api
+		.get_storage_double_map_key_prefix(ENCOINTER_CEREMONIES, "Endorsees", community_ceremony)
+		.await
+		.unwrap();
+	let page_size = 1000;
+	let mut endorsees = Vec::new();
+	let mut start_key = None;
+	loop {
+		let storage_keys = api
+			.get_storage_keys_paged(Some(key_prefix.clone()), page_size, start_key, maybe_at)
+			.await
+			.unwrap();
+		endorsees.extend(
+			storage_keys
+				.iter()
+				.map(|key| AccountId::decode(&mut key.0[key.0.len() - 32..].as_ref()).unwrap()),
+		);
+		if storage_keys.len() < page_size as usize {
+			break
+		}
+		start_key = storage_keys.last().cloned();
+	}
+	endorsees
+}
@@ -0,0 +1,125 @@
+//! Offline tools around community identifiers.
+//!
+//! A community identifier is the 5 character geohash of the community's first location followed by
+//! the base58 encoded 4 byte digest of its bootstrappers.
+
+use crate::{community_spec::parse_community_identifier, utils::keys::get_accountid_from_str};
+use encointer_node_runtime::AccountId;
+use encointer_primitives::communities::{CommunityIdentifier, Degree, GeoHash, Location};
+
+const GEOHASH_LENGTH: usize = 5;
+const DIGEST_LENGTH: usize = 4;
+
+/// Bounding box of a geohash cell in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoHashCell {
+	pub min_lat: f64,
+	pub max_lat: f64,
+	pub min_lon: f64,
+	pub max_lon: f64,
+}
+
+impl GeoHashCell {
+	pub fn center(&self) -> (f64, f64) {
+		((self.min_lat + self.max_lat) / 2.0, (self.min_lon + self.max_lon) / 2.0)
+	}
+}
+
+/// Decodes a base32 geohash of a community identifier into its cell.
+pub fn decode_geohash(geohash: &str) -> Result<GeoHashCell, String> {
+	let (lon, lat, lon_err, lat_err) = GeoHash::try_from(geohash)
+		.and_then(|g| g.try_as_coordinates())
+		.map_err(|e| format!("invalid geohash '{geohash}': {e:?}"))?;
+	let (lon, lat, lon_err, lat_err) = (
+		lon.to_num::<f64>(),
+		lat.to_num::<f64>(),
+		lon_err.to_num::<f64>(),
+		lat_err.to_num::<f64>(),
+	);
+	Ok(GeoHashCell {
+		min_lat: lat - lat_err,
+		max_lat: lat + lat_err,
+		min_lon: lon - lon_err,
+		max_lon: lon + lon_err,
+	})
+}
+
+fn parse_location(location: &str) -> Location {
+	let (lat, lon) = location.split_once(',').expect("location must be given as lat,lon");
+	let parse = |v: &str| v.trim().parse::<f64>().expect("invalid coordinate");
+	Location { lat: Degree::from_num(parse(lat)), lon: Degree::from_num(parse(lon)) }
+}
+
+pub fn compute_cid(location: &str, bootstrappers: &[String]) {
+	let location = parse_location(location);
+	let bootstrappers: Vec<AccountId> =
+		bootstrappers.iter().map(|b| get_accountid_from_str(b)).collect();
+	let cid = CommunityIdentifier::new(location, bootstrappers).expect("invalid location");
+	println!("{cid}");
+}
+
+pub fn decode_cid(cid: &str) {
+	let cid = parse_community_identifier(cid)
+		.unwrap_or_else(|| panic!("invalid community identifier '{cid}'"))
+		.to_string();
+	let (geohash, digest) = cid.split_at(GEOHASH_LENGTH);
+	let digest = bs58::decode(digest).into_vec().expect("digest is validated");
+	let cell = decode_geohash(geohash).unwrap_or_else(|e| panic!("{e}"));
+	let (lat, lon) = cell.center();
+	println!("cid: {cid}");
+	println!("geohash: {geohash}");
+	println!(
+		"bounding box: lat {} .. {}, lon {} .. {}",
+		cell.min_lat, cell.max_lat, cell.min_lon, cell.max_lon
+	);
+	println!("center: {lat},{lon}");
+	println!("digest: 0x{}", hex::encode(digest));
+}
+
+pub fn encode_cid(geohash: &str, digest: &str) {
+	decode_geohash(geohash).unwrap_or_else(|e| panic!("{e}"));
+	let digest = hex::decode(digest.trim_start_matches("0x")).expect("digest must be hex");
+	if digest.len() != DIGEST_LENGTH {
+		panic!("digest must have {DIGEST_LENGTH} bytes");
+	}
+	let cid =
+		parse_community_identifier(&format!("{geohash}{}", bs58::encode(digest).into_string()))
+			.expect("geohash and digest are validated");
+	println!("{cid}");
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decode_geohash_matches_reference_cell() {
+		// 9q60y of the geohash crate's documentation, center lon -120.65185546875, lat 35.31005859375
+		let cell = decode_geohash("9q60y").unwrap();
+		assert_eq!(
+			cell,
+			GeoHashCell {
+				min_lat: 35.2880859375,
+				max_lat: 35.33203125,
+				min_lon: -120.673828125,
+				max_lon: -120.6298828125,
+			}
+		);
+		assert_eq!(cell.center(), (35.31005859375, -120.65185546875));
+	}
+
+	#[test]
+	fn decode_geohash_rejects_invalid_geohashes() {
+		assert!(decode_geohash("9q60").is_err());
+		assert!(decode_geohash("9q60a").is_err());
+	}
+
+	#[test]
+	fn decoded_cid_location_lies_in_its_cell() {
+		let location = parse_location("47.3769,8.5417");
+		let cid = CommunityIdentifier::new::<AccountId>(location, vec![]).unwrap().to_string();
+		let cell = decode_geohash(&cid[..GEOHASH_LENGTH]).unwrap();
+		assert!(cell.min_lat <= 47.3769 && 47.3769 < cell.max_lat);
+		assert!(cell.min_lon <= 8.5417 && 8.5417 < cell.max_lon);
+	}
+}
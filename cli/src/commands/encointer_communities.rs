@@ -1,8 +1,15 @@
 use crate::{
 	cli::Cli,
+	commands::encointer_ceremonies::get_bootstrappers,
 	community_spec::{
-		add_location_call, new_community_call, read_community_spec_from_file, AddLocationCall,
-		CommunitySpec,
+		add_location_call, community_spec_json_schema, demurrage_per_block_from_halving_blocks,
+		halving_blocks_from_demurrage_per_block,
+		lint_community_spec as lint_community_spec_offline, migrate_community_spec,
+		new_community_call, parse_community_spec, purge_community_call,
+		read_community_spec_from_file, set_u32_parameter_call, solar_trip_time_s,
+		update_community_metadata_call, update_demurrage_call, update_nominal_income_call,
+		AddLocationCall, CommunitySection, CommunitySpec, CommunitySpecFile, LintFinding,
+		LintLevel, LocationFeature, MetaSection, SpecError, COMMUNITY_SPEC_VERSION,
 	},
 	exit_code,
 	utils::{
@@ -12,12 +19,16 @@ use crate::{
 	},
 };
 use encointer_api_client_extension::{
-	set_api_extrisic_params_builder, CommunitiesApi, ParentchainExtrinsicSigner, SchedulerApi,
+	haversine_distance, relevant_geohashes, set_api_extrisic_params_builder, Api, CommunitiesApi,
+	ParentchainExtrinsicSigner, SchedulerApi,
+};
+use encointer_primitives::{
+	balances::{BalanceType, Demurrage},
+	communities::{CommunityIdentifier, Degree, GeoHash, Location},
 };
-use encointer_primitives::communities::{CommunityIdentifier, GeoHash, Location};
 
 use crate::{
-	community_spec::remove_location_call,
+	community_spec::{remove_location_call, RemoveLocationCall},
 	utils::{send_and_wait_for_finalized, BatchCall, CallWrapping},
 };
 use encointer_primitives::scheduler::CeremonyPhaseType;
@@ -28,7 +39,7 @@ use sp_application_crypto::Ss58Codec;
 use sp_core::Pair;
 use sp_keyring::Sr25519Keyring as AccountKeyring;
 use std::str::FromStr;
-use substrate_api_client::ac_node_api::Metadata;
+use substrate_api_client::{ac_node_api::Metadata, GetStorage};
 
 pub async fn new_community(
 	cli: &Cli,
@@ -41,6 +52,14 @@ pub async fn new_community(
 	// -----setup
 	let spec = read_community_spec_from_file(spec_file);
 	let cid = spec.community_identifier();
+	if spec.derived_community_identifier() != Some(cid) {
+		error!(
+			"community.cid {} of {} does not match the identifier derived from the first location \
+			 and the bootstrappers",
+			cid, spec_file
+		);
+		std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+	}
 
 	let signer = signer_arg
 		.map_or_else(|| AccountKeyring::Alice.pair(), |signer| get_pair_from_str(signer).into());
@@ -52,71 +71,28 @@ pub async fn new_community(
 	// ------- create calls for xt's
 	let new_community_call = OpaqueCall::from_tuple(&new_community_call(&spec, api.metadata()));
 	// only the first meetup location has been registered now. register all others one-by-one
-	let add_location_batch_calls =
-		create_add_location_batches(api.metadata(), spec.locations(), cid, batch_size);
-
-	let call_wrapping = CallWrapping::from_str(wrap_call).unwrap_or(CallWrapping::None);
-	info!("XT call wrapping: {:?}", call_wrapping);
-
-	let (new_community_final_call, add_location_batch_final_call) = match call_wrapping {
-		CallWrapping::None => (
-			new_community_call,
-			add_location_batch_calls
-				.into_iter()
-				.map(|c| OpaqueCall::from_tuple(&c))
-				.collect::<Vec<_>>(),
-		),
-		CallWrapping::Sudo => {
-			if !contains_sudo_pallet(api.metadata()) {
-				panic!("Want to wrap call with sudo, but sudo does not exist on this chain.");
-			}
-
-			let sudo_new_community = sudo_call(api.metadata(), new_community_call);
-			let sudo_add_location_batch = add_location_batch_calls
-				.into_iter()
-				.map(|call| sudo_call(api.metadata(), call))
-				.collect::<Vec<_>>();
-			info!("Printing raw sudo calls for js/apps for cid: {}", cid);
-			print_raw_call("sudo(new_community)", &sudo_new_community);
-
-			for call in sudo_add_location_batch.iter() {
-				print_raw_call("sudo(utility_batch(add_location))", &call);
-			}
-
-			let opaque_sudo_add_location = sudo_add_location_batch
-				.into_iter()
-				.map(|call| OpaqueCall::from_tuple(&call))
-				.collect();
-
-			(OpaqueCall::from_tuple(&sudo_new_community), opaque_sudo_add_location)
-		},
-		CallWrapping::Collective => {
-			let threshold = (get_councillors(&api).await.unwrap().len() / 2 + 1) as u32;
-			info!(
-				"Printing raw collective propose calls with threshold {} for js/apps for cid: {}",
-				threshold, cid
-			);
-			let propose_new_community =
-				collective_propose_call(api.metadata(), threshold, new_community_call);
-			print_raw_call("collective_propose(new_community)", &propose_new_community);
-
-			let propose_add_location_batch = add_location_batch_calls
-				.into_iter()
-				.map(|call| collective_propose_call(api.metadata(), threshold, call))
-				.collect::<Vec<_>>();
-
-			for call in propose_add_location_batch.iter() {
-				print_raw_call("collective_propose(utility_batch(add_location))", &call);
-			}
-
-			let opaque_collective_add_location = propose_add_location_batch
-				.into_iter()
-				.map(|call| OpaqueCall::from_tuple(&call))
-				.collect();
-
-			(OpaqueCall::from_tuple(&propose_new_community), opaque_collective_add_location)
-		},
-	};
+	let add_location_batch_calls = create_add_location_batches(
+		api.metadata(),
+		spec.locations().into_iter().skip(1).collect(),
+		cid,
+		batch_size,
+	);
+
+	info!("Printing raw calls for js/apps for cid: {}", cid);
+	let mut final_calls = wrap_privileged_calls(
+		&api,
+		wrap_call,
+		std::iter::once(("new_community", new_community_call))
+			.chain(
+				add_location_batch_calls
+					.iter()
+					.map(|c| ("utility_batch(add_location)", OpaqueCall::from_tuple(c))),
+			)
+			.collect(),
+	)
+	.await;
+	let add_location_batch_final_call = final_calls.split_off(1);
+	let new_community_final_call = final_calls.remove(0);
 
 	if !dryrun {
 		info!("Sending transactions");
@@ -341,7 +317,6 @@ fn create_add_location_batches(
 
 	locations
 		.into_iter()
-		.skip(1) // Skip the first location
 		.map(|l| add_location_call(metadata, cid, l))
 		.chunks(batch_size as usize)
 		.into_iter()
@@ -349,3 +324,614 @@ fn create_add_location_batches(
 		.map(|b| batch_call(metadata, b))
 		.collect() // Collect all batches into a Vec of BatchCall
 }
+
+fn create_remove_location_batches(
+	metadata: &Metadata,
+	locations: Vec<Location>,
+	cid: CommunityIdentifier,
+	batch_size: u32,
+) -> Vec<BatchCall<RemoveLocationCall>> {
+	info!("Creating remove location batches of size: {:?}", batch_size);
+
+	locations
+		.into_iter()
+		.map(|l| remove_location_call(metadata, cid, l))
+		.chunks(batch_size as usize)
+		.into_iter()
+		.map(|chunk| chunk.collect())
+		.map(|b| batch_call(metadata, b))
+		.collect()
+}
+
+pub async fn lint_community_spec(cli: &Cli, spec_file: &str, offline: bool) {
+	let spec_str = std::fs::read_to_string(spec_file).unwrap_or_else(|e| {
+		println!("error: {spec_file}: {e}");
+		std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+	});
+	let spec = match parse_community_spec(&spec_str) {
+		Ok(spec) => spec,
+		Err(errors) => {
+			for e in errors.iter() {
+				println!("{}", LintFinding::error(e.path.clone(), e.message.clone()));
+			}
+			println!("{} errors, 0 warnings", errors.len());
+			std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+		},
+	};
+	let mut findings = lint_community_spec_offline(&spec);
+	let is_valid = |findings: &[LintFinding]| !findings.iter().any(|f| f.level == LintLevel::Error);
+
+	// chain checks need well-formed locations and bootstrappers
+	if !offline && is_valid(&findings) {
+		let api = get_chain_api(cli).await;
+		let maybe_at = cli.at_block();
+		let min_solar_trip_time_s: u32 = api
+			.get_storage("EncointerCommunities", "MinSolarTripTimeS", maybe_at)
+			.await
+			.unwrap()
+			.unwrap_or(1);
+		let max_speed_mps: u32 = api
+			.get_storage("EncointerCommunities", "MaxSpeedMps", maybe_at)
+			.await
+			.unwrap()
+			.unwrap_or(1);
+		info!("minSolarTripTimeS: {}, maxSpeedMps: {}", min_solar_trip_time_s, max_speed_mps);
+
+		let locations = spec.locations();
+		for (i, a) in locations.iter().enumerate() {
+			for (j, b) in locations.iter().enumerate().skip(i + 1) {
+				if a != b && solar_trip_time_s(a, b, max_speed_mps) < min_solar_trip_time_s {
+					findings.push(LintFinding::error(
+						format!("features[{j}]"),
+						format!(
+							"too close to features[{i}] ({} m), solar trip time must be at least {min_solar_trip_time_s} s",
+							haversine_distance(a, b)
+						),
+					));
+				}
+			}
+		}
+
+		let cids = api.get_community_identifiers(maybe_at).await.unwrap_or_default();
+		for (i, location) in locations.iter().enumerate() {
+			for geohash in relevant_geohashes(location) {
+				for cid in cids.iter() {
+					let existing = api
+						.get_locations_by_geohash(*cid, geohash.clone(), maybe_at)
+						.await
+						.unwrap_or_default();
+					for e in existing.iter() {
+						if e == location {
+							findings.push(LintFinding::error(
+								format!("features[{i}]"),
+								format!("already a location of community {cid}"),
+							));
+						} else if solar_trip_time_s(location, e, max_speed_mps) <
+							min_solar_trip_time_s
+						{
+							findings.push(LintFinding::error(
+								format!("features[{i}]"),
+								format!(
+									"too close to location (lat: {} lon: {}) of community {cid} ({} m)",
+									e.lat,
+									e.lon,
+									haversine_distance(location, e)
+								),
+							));
+						}
+					}
+				}
+			}
+		}
+
+		let cid = spec.community_identifier();
+		if cids.contains(&cid) {
+			findings.push(LintFinding::error(
+				"features[0]",
+				format!("community identifier {cid} is already taken"),
+			));
+		} else {
+			println!("community identifier: {cid}");
+		}
+	}
+
+	for f in findings.iter() {
+		println!("{f}");
+	}
+	let errors = findings.iter().filter(|f| f.level == LintLevel::Error).count();
+	println!("{errors} errors, {} warnings", findings.len() - errors);
+	if !is_valid(&findings) {
+		std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+	}
+}
+
+fn write_or_print(out: Option<&str>, content: &str) {
+	match out {
+		Some(path) => {
+			std::fs::write(path, content).expect("could not write output file");
+			println!("{path}");
+		},
+		None => println!("{content}"),
+	}
+}
+
+pub fn print_community_spec_schema(out: Option<&str>) {
+	write_or_print(out, &community_spec_json_schema());
+}
+
+/// Rewrites a spec in the current format version, keeping keys unknown to this client.
+pub fn migrate_community_spec_file(spec_file: &str, out: Option<&str>) {
+	let spec_str = std::fs::read_to_string(spec_file).expect("could not read spec file");
+	let migrated = serde_json::from_str(&spec_str)
+		.map_err(|e| {
+			SpecError::new(format!("line {} column {}", e.line(), e.column()), e.to_string())
+		})
+		.and_then(migrate_community_spec)
+		.unwrap_or_else(|e| {
+			error!("cannot migrate {spec_file}: {e}");
+			std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+		});
+	let migrated = serde_json::to_string_pretty(&migrated).unwrap();
+	if let Err(errors) = parse_community_spec(&migrated) {
+		for e in errors {
+			error!("invalid community spec {spec_file}: {e}");
+		}
+		std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+	}
+	write_or_print(out, &migrated);
+}
+
+/// Wraps privileged calls with sudo or a council proposal as requested by `wrap_call` and prints
+/// the raw calls for js/apps, labeled with their name.
+async fn wrap_privileged_calls(
+	api: &Api,
+	wrap_call: &str,
+	calls: Vec<(&str, OpaqueCall)>,
+) -> Vec<OpaqueCall> {
+	let call_wrapping = CallWrapping::from_str(wrap_call).unwrap_or(CallWrapping::None);
+	info!("XT call wrapping: {:?}", call_wrapping);
+	match call_wrapping {
+		CallWrapping::None => calls
+			.into_iter()
+			.map(|(name, call)| {
+				print_raw_call(name, &call);
+				call
+			})
+			.collect(),
+		CallWrapping::Sudo => {
+			if !contains_sudo_pallet(api.metadata()) {
+				panic!("Want to wrap call with sudo, but sudo does not exist on this chain.");
+			}
+			calls
+				.into_iter()
+				.map(|(name, call)| {
+					let sudo = sudo_call(api.metadata(), call);
+					print_raw_call(&format!("sudo({name})"), &sudo);
+					OpaqueCall::from_tuple(&sudo)
+				})
+				.collect()
+		},
+		CallWrapping::Collective => {
+			let threshold = (get_councillors(api).await.unwrap().len() / 2 + 1) as u32;
+			info!("collective proposal threshold: {}", threshold);
+			calls
+				.into_iter()
+				.map(|(name, call)| {
+					let propose = collective_propose_call(api.metadata(), threshold, call);
+					print_raw_call(&format!("collective_propose({name})"), &propose);
+					OpaqueCall::from_tuple(&propose)
+				})
+				.collect()
+		},
+	}
+}
+
+/// Wraps a privileged call as requested, prints the raw calls for js/apps and submits it unless
+/// `dryrun`.
+async fn submit_privileged_call<C: Encode>(
+	cli: &Cli,
+	api: &mut Api,
+	name: &str,
+	call: C,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+) {
+	let final_call =
+		wrap_privileged_calls(api, wrap_call, vec![(name, OpaqueCall::from_tuple(&call))])
+			.await
+			.remove(0);
+
+	if dryrun {
+		println!("0x{}", hex::encode(final_call.encode()));
+		return
+	}
+	let signer = signer_arg
+		.map_or_else(|| AccountKeyring::Alice.pair(), |signer| get_pair_from_str(signer).into());
+	info!("signer ss58 is {}", signer.public().to_ss58check());
+	api.set_signer(ParentchainExtrinsicSigner::new(signer));
+	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
+	set_api_extrisic_params_builder(api, tx_payment_cid_arg).await;
+	send_and_wait_for_in_block(api, xt(api, final_call).await, tx_payment_cid_arg).await;
+}
+
+/// Prints `field: current -> new` and returns whether the value changes.
+fn print_diff(field: &str, current: impl std::fmt::Display, new: impl std::fmt::Display) -> bool {
+	let (current, new) = (current.to_string(), new.to_string());
+	if current == new {
+		println!("  {field}: {current} (unchanged)");
+		false
+	} else {
+		println!("* {field}: {current} -> {new}");
+		true
+	}
+}
+
+fn lossy_string<B: AsRef<[u8]>>(bytes: Option<B>) -> String {
+	bytes.map_or("none".to_string(), |b| String::from_utf8_lossy(b.as_ref()).to_string())
+}
+
+fn describe_demurrage(demurrage: Option<Demurrage>) -> String {
+	match demurrage {
+		Some(d) => match halving_blocks_from_demurrage_per_block(d) {
+			Some(blocks) => format!("{d} per block (halving after {blocks} blocks)"),
+			None => format!("{d} per block"),
+		},
+		None => "chain default".to_string(),
+	}
+}
+
+async fn admin_cid(cli: &Cli, api: &Api) -> CommunityIdentifier {
+	match cli.cid.as_deref() {
+		Some(cid) => api.verify_cid(cid, None).await,
+		None => {
+			error!("please supply argument --cid");
+			std::process::exit(exit_code::NO_CID_SPECIFIED);
+		},
+	}
+}
+
+pub async fn update_community_metadata(
+	cli: &Cli,
+	spec_file: &str,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+) {
+	let spec = read_community_spec_from_file(spec_file);
+	let mut api = get_chain_api(cli).await;
+	let cid = match cli.cid.as_deref() {
+		Some(cid) => api.verify_cid(cid, None).await,
+		None => api.verify_cid(&spec.community_identifier().to_string(), None).await,
+	};
+	let new = spec.metadata();
+	if let Err(e) = new.validate() {
+		error!("invalid community metadata in {}: {:?}", spec_file, e);
+		std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+	}
+	let current = api.get_community_metadata(cid, None).await.unwrap_or_default();
+
+	println!("community metadata of {cid}:");
+	let mut changed =
+		print_diff("name", lossy_string(Some(&current.name)), lossy_string(Some(&new.name)));
+	changed |=
+		print_diff("symbol", lossy_string(Some(&current.symbol)), lossy_string(Some(&new.symbol)));
+	changed |=
+		print_diff("assets", lossy_string(Some(&current.assets)), lossy_string(Some(&new.assets)));
+	changed |= print_diff(
+		"theme",
+		lossy_string(current.theme.as_deref()),
+		lossy_string(new.theme.as_deref()),
+	);
+	changed |=
+		print_diff("url", lossy_string(current.url.as_deref()), lossy_string(new.url.as_deref()));
+	changed |= print_diff(
+		"announcementSigner",
+		format!("{:?}", current.announcement_signer),
+		format!("{:?}", new.announcement_signer),
+	);
+	changed |= print_diff("rules", format!("{:?}", current.rules), format!("{:?}", new.rules));
+
+	let bootstrappers = get_bootstrappers(&api, cid, None).await.unwrap();
+	if spec.bootstrappers() != bootstrappers {
+		warn!(
+			"bootstrappers in {} differ from chain state. They are part of the community identifier and can't be updated",
+			spec_file
+		);
+	}
+	if !changed {
+		println!("nothing to update");
+		return
+	}
+
+	let call = update_community_metadata_call(api.metadata(), cid, new);
+	submit_privileged_call(
+		cli,
+		&mut api,
+		"update_community_metadata",
+		call,
+		signer_arg,
+		dryrun,
+		wrap_call,
+	)
+	.await;
+}
+
+pub async fn set_demurrage(
+	cli: &Cli,
+	demurrage_halving_blocks: u64,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+) {
+	let mut api = get_chain_api(cli).await;
+	let cid = admin_cid(cli, &api).await;
+	let new = demurrage_per_block_from_halving_blocks(demurrage_halving_blocks);
+	let current = api.get_demurrage_per_block(cid, None).await;
+
+	println!("demurrage of {cid}:");
+	if !print_diff("demurrage", describe_demurrage(current), describe_demurrage(Some(new))) {
+		println!("nothing to update");
+		return
+	}
+	let call = update_demurrage_call(api.metadata(), cid, new);
+	submit_privileged_call(cli, &mut api, "update_demurrage", call, signer_arg, dryrun, wrap_call)
+		.await;
+}
+
+pub async fn set_nominal_income(
+	cli: &Cli,
+	nominal_income: f64,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+) {
+	let mut api = get_chain_api(cli).await;
+	let cid = admin_cid(cli, &api).await;
+	let new = BalanceType::from_num(nominal_income);
+	let current = api.get_nominal_income(cid, None).await;
+
+	println!("nominal income of {cid}:");
+	if !print_diff(
+		"nominal income",
+		current.map_or("chain default".to_string(), |i| i.to_string()),
+		new,
+	) {
+		println!("nothing to update");
+		return
+	}
+	let call = update_nominal_income_call(api.metadata(), cid, new);
+	submit_privileged_call(
+		cli,
+		&mut api,
+		"update_nominal_income",
+		call,
+		signer_arg,
+		dryrun,
+		wrap_call,
+	)
+	.await;
+}
+
+/// Sets one of the global `u32` parameters of `EncointerCommunities`, like `MinSolarTripTimeS`.
+pub async fn set_communities_parameter(
+	cli: &Cli,
+	storage: &'static str,
+	call_name: &str,
+	value: u32,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+) {
+	let mut api = get_chain_api(cli).await;
+	let current: Option<u32> =
+		api.get_storage("EncointerCommunities", storage, None).await.unwrap();
+	if !print_diff(storage, current.map_or("unset".to_string(), |v| v.to_string()), value) {
+		println!("nothing to update");
+		return
+	}
+	let call = set_u32_parameter_call(api.metadata(), call_name, value);
+	submit_privileged_call(cli, &mut api, call_name, call, signer_arg, dryrun, wrap_call).await;
+}
+
+/// Purging can't be undone, so the call is only submitted with `yes` after showing what is
+/// removed.
+pub async fn purge_community(
+	cli: &Cli,
+	yes: bool,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+) {
+	let mut api = get_chain_api(cli).await;
+	let cid = admin_cid(cli, &api).await;
+	let meta = api.get_community_metadata(cid, None).await.unwrap_or_default();
+	let locations = api.get_locations(cid).await.unwrap_or_default();
+	println!(
+		"* {} ({}) with {} locations will be removed",
+		cid,
+		String::from_utf8_lossy(&meta.name),
+		locations.len()
+	);
+	if !yes && !dryrun {
+		println!("nothing submitted. Pass --yes to purge {cid}");
+		return
+	}
+	let call = purge_community_call(api.metadata(), cid);
+	submit_privileged_call(cli, &mut api, "purge_community", call, signer_arg, dryrun, wrap_call)
+		.await;
+}
+
+pub async fn sync_locations(
+	cli: &Cli,
+	spec_file: &str,
+	signer_arg: Option<&str>,
+	dryrun: bool,
+	wrap_call: &str,
+	batch_size: u32,
+) {
+	let spec = read_community_spec_from_file(spec_file);
+	let mut api = get_chain_api(cli).await;
+	let cid = admin_cid(cli, &api).await;
+
+	let wanted = spec.locations();
+	let existing = api.get_locations(cid).await.unwrap();
+	// remove first, so moved locations don't conflict with their old position
+	let to_remove: Vec<Location> =
+		existing.iter().filter(|l| !wanted.contains(l)).copied().collect();
+	let mut to_add: Vec<Location> = Vec::new();
+	for l in wanted.iter().filter(|l| !existing.contains(l)) {
+		if !to_add.contains(l) {
+			to_add.push(*l);
+		}
+	}
+
+	println!("locations of {cid}:");
+	for l in to_remove.iter() {
+		println!("- lat: {} lon: {}", l.lat, l.lon);
+	}
+	for l in to_add.iter() {
+		println!("+ lat: {} lon: {}", l.lat, l.lon);
+	}
+	println!(
+		"{} on chain, {} in {}: {} to remove, {} to add, {} unchanged",
+		existing.len(),
+		wanted.len(),
+		spec_file,
+		to_remove.len(),
+		to_add.len(),
+		existing.len() - to_remove.len()
+	);
+	if to_remove.is_empty() && to_add.is_empty() {
+		println!("nothing to update");
+		return
+	}
+	if to_add.is_empty() && to_remove.len() == existing.len() {
+		warn!("this removes all locations of {}", cid);
+	}
+
+	if !dryrun && api.get_current_phase(None).await.unwrap() != CeremonyPhaseType::Registering {
+		error!("Wrong ceremony phase for registering new locations for {}", cid);
+		error!("Aborting without changing locations");
+		std::process::exit(exit_code::WRONG_PHASE);
+	}
+
+	for batch in create_remove_location_batches(api.metadata(), to_remove, cid, batch_size) {
+		submit_privileged_call(
+			cli,
+			&mut api,
+			"utility_batch(remove_location)",
+			batch,
+			signer_arg,
+			dryrun,
+			wrap_call,
+		)
+		.await;
+	}
+	for batch in create_add_location_batches(api.metadata(), to_add, cid, batch_size) {
+		submit_privileged_call(
+			cli,
+			&mut api,
+			"utility_batch(add_location)",
+			batch,
+			signer_arg,
+			dryrun,
+			wrap_call,
+		)
+		.await;
+	}
+}
+
+/// Reconstructs the spec of a registered community. The location the community identifier was
+/// derived from comes first, so the identifier derived from the exported spec matches the pinned
+/// `community.cid`.
+pub async fn export_community(cli: &Cli, out: Option<&str>) {
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let cid = admin_cid(cli, &api).await;
+	let meta = api
+		.get_community_metadata(cid, maybe_at)
+		.await
+		.expect("no metadata found, does the community exist?");
+	let bootstrappers = get_bootstrappers(&api, cid, maybe_at).await.unwrap();
+	let mut locations = api.get_locations_at(cid, maybe_at).await.unwrap();
+	match locations
+		.iter()
+		.position(|l| CommunityIdentifier::new(*l, bootstrappers.clone()).ok() == Some(cid))
+	{
+		Some(i) => locations.swap(0, i),
+		None => warn!(
+			"none of the locations yields cid {}, the exported spec can't register it again",
+			cid
+		),
+	}
+
+	let lossy = |b: &[u8]| String::from_utf8_lossy(b).to_string();
+	let spec = CommunitySpecFile {
+		version: COMMUNITY_SPEC_VERSION,
+		geojson_type: "FeatureCollection".into(),
+		community: Some(CommunitySection {
+			cid: Some(cid.to_string()),
+			meta: MetaSection {
+				name: lossy(&meta.name),
+				symbol: lossy(&meta.symbol),
+				assets: lossy(&meta.assets),
+				theme: meta.theme.as_deref().map(|t| lossy(t.as_slice())),
+				url: meta.url.as_deref().map(|u| lossy(u.as_slice())),
+				announcement_signer: meta.announcement_signer.clone(),
+				rules: meta.rules,
+			},
+			bootstrappers: bootstrappers.iter().map(|b| b.to_ss58check()).collect(),
+			demurrage_halving_blocks: api
+				.get_demurrage_per_block(cid, maybe_at)
+				.await
+				.and_then(halving_blocks_from_demurrage_per_block),
+			ceremony_income: api
+				.get_nominal_income(cid, maybe_at)
+				.await
+				.map(|income| income.to_num::<f64>()),
+		}),
+		features: locations.iter().map(LocationFeature::from_location).collect(),
+	};
+	info!("exported {} locations of {}", spec.features.len(), cid);
+	write_or_print(out, &serde_json::to_string_pretty(&spec).unwrap());
+}
+
+pub async fn list_nearby(cli: &Cli, lat: f64, lon: f64, radius_km: f64) {
+	let api = get_chain_api(cli).await;
+	let center = Location { lat: Degree::from_num(lat), lon: Degree::from_num(lon) };
+	let nearby = api
+		.get_locations_nearby(center, (radius_km * 1000.0).round() as u32, cli.at_block())
+		.await;
+	let names = api.get_cid_names().await.unwrap_or_default();
+	let name = |cid: &CommunityIdentifier| {
+		names
+			.iter()
+			.find(|n| n.cid == *cid)
+			.map_or("?".to_string(), |n| String::from_utf8_lossy(&n.name).to_string())
+	};
+
+	println!("communities within {radius_km} km of lat: {lat} lon: {lon}:");
+	// locations are sorted by distance, so the first one of each community is its nearest
+	let mut communities: Vec<(CommunityIdentifier, u32, usize)> = Vec::new();
+	for n in nearby.iter() {
+		match communities.iter_mut().find(|(cid, _, _)| *cid == n.cid) {
+			Some((_, _, count)) => *count += 1,
+			None => communities.push((n.cid, n.distance_m, 1)),
+		}
+	}
+	for (cid, nearest, count) in communities.iter() {
+		println!("{}: {}, {} locations, nearest at {} m", cid, name(cid), count, nearest);
+	}
+	println!("distance [m], cid, name, lat, lon");
+	for n in nearby.iter() {
+		println!(
+			"{}, {}, {}, {}, {}",
+			n.distance_m,
+			n.cid,
+			name(&n.cid),
+			n.location.lat,
+			n.location.lon
+		);
+	}
+}
@@ -0,0 +1,254 @@
+//! Early warning for communities which are about to be purged for inactivity.
+//!
+//! At the start of every Assigning phase the ceremonies pallet resets `InactivityCounters(cid)` of
+//! every community with `IssuedRewards` in the previous ceremony and increments it otherwise. A
+//! community is purged at the Assigning phase in which its counter would exceed
+//! `InactivityTimeout`.
+
+use crate::{
+	cli::Cli,
+	commands::{
+		encointer_core::get_community_balance,
+		encointer_history::{get_count, last_block_of_ceremony},
+		frame::{get_block_hash, get_block_number},
+	},
+	utils::get_chain_api,
+};
+use encointer_api_client_extension::{
+	Api, CeremoniesApi, CommunitiesApi, SchedulerApi, TreasuriesApi, ENCOINTER_CEREMONIES,
+};
+use encointer_node_runtime::{AccountId, Balance, Hash, Nonce};
+use encointer_primitives::{
+	ceremonies::{CeremonyIndexType, ReputationCountType},
+	communities::CommunityIdentifier,
+};
+use log::info;
+use serde::Serialize;
+use substrate_api_client::{
+	ac_primitives::{AccountData, AccountInfo},
+	GetStorage,
+};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CeremonyActivity {
+	pub cindex: CeremonyIndexType,
+	/// Bootstrappers, reputables, endorsees and newbies registered.
+	pub registrations: u64,
+	/// Attendees who received reputation. `None` for the ongoing ceremony.
+	pub attested: Option<ReputationCountType>,
+	pub locations: usize,
+	pub treasury_native_balance: Option<Balance>,
+	pub treasury_community_balance: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityHealth {
+	pub cid: String,
+	pub name: String,
+	pub last_attested_ceremony: Option<CeremonyIndexType>,
+	pub inactivity_counter: u32,
+	/// Number of further ceremonies without attested attendees the community survives.
+	pub remaining_inactive_cycles: u32,
+	/// Oldest ceremony first, the ongoing ceremony last. Each ceremony is queried at its last
+	/// block, the ongoing one at head.
+	pub activity: Vec<CeremonyActivity>,
+	/// Negative if registrations declined from the oldest to the latest completed ceremony.
+	pub registration_trend: i64,
+	pub active_locations: usize,
+	/// Change in the number of locations from the oldest ceremony to head.
+	pub location_trend: i64,
+	pub treasury_account: Option<String>,
+	pub treasury_native_balance: Option<Balance>,
+	pub treasury_community_balance: Option<f64>,
+	/// Change in the treasury balances from the oldest ceremony to head.
+	pub treasury_native_balance_trend: Option<i128>,
+	pub treasury_community_balance_trend: Option<f64>,
+	pub at_risk: bool,
+	pub reasons: Vec<String>,
+}
+
+pub async fn print_community_health(cli: &Cli, cycles: u32, warn_cycles: u32) {
+	let api = get_chain_api(cli).await;
+	let head = get_block_number(&api, cli.at_block()).await;
+	let head_hash = get_block_hash(&api, head).await;
+	let maybe_at = Some(head_hash);
+	let cindex = api.get_ceremony_index(maybe_at).await;
+	let inactivity_timeout: u32 = api
+		.get_storage(ENCOINTER_CEREMONIES, "InactivityTimeout", maybe_at)
+		.await
+		.unwrap()
+		.unwrap_or_default();
+	info!("cindex: {}, inactivity timeout: {}", cindex, inactivity_timeout);
+
+	let cids = match cli.cid.as_deref() {
+		Some(cid) => vec![api.verify_cid(cid, maybe_at).await],
+		None => api.get_community_identifiers(maybe_at).await.unwrap_or_default(),
+	};
+	let names = api.get_cid_names().await.unwrap_or_default();
+
+	// registries are purged after a while, so completed ceremonies are queried at their last block
+	let first = cindex.saturating_sub(cycles).max(1);
+	let mut cycle_ends = Vec::new();
+	for c in first..cindex {
+		if let Some(n) = last_block_of_ceremony(&api, c, head).await {
+			let at = get_block_hash(&api, n).await;
+			let existing = api.get_community_identifiers(Some(at)).await.unwrap_or_default();
+			cycle_ends.push((c, at, existing));
+		}
+	}
+	cycle_ends.push((cindex, head_hash, cids.clone()));
+
+	let mut report = Vec::new();
+	for cid in cids {
+		let inactivity_counter: u32 = api
+			.get_storage_map(ENCOINTER_CEREMONIES, "InactivityCounters", cid, maybe_at)
+			.await
+			.unwrap()
+			.unwrap_or_default();
+		let remaining_inactive_cycles = inactivity_timeout.saturating_sub(inactivity_counter);
+
+		let treasury = api.get_community_treasury_account_unchecked(Some(cid)).await;
+
+		let mut activity = Vec::new();
+		for (c, at, existing) in cycle_ends.iter() {
+			// the community did not exist yet at the end of this ceremony
+			if !existing.contains(&cid) {
+				continue
+			}
+			let mut registrations = 0;
+			for count in ["BootstrapperCount", "ReputableCount", "EndorseeCount", "NewbieCount"] {
+				registrations += get_count(&api, count, cid, *c, *at).await;
+			}
+			let attested = if *c < cindex {
+				Some(api.get_reputation_count((cid, *c), Some(*at)).await.unwrap_or(0))
+			} else {
+				None
+			};
+			let (treasury_native_balance, treasury_community_balance) = match &treasury {
+				Some(account) => {
+					let (native, community) = get_treasury_balances(&api, cid, account, *at).await;
+					(Some(native), Some(community))
+				},
+				None => (None, None),
+			};
+			activity.push(CeremonyActivity {
+				cindex: *c,
+				registrations,
+				attested,
+				locations: api.get_locations_at(cid, Some(*at)).await.map_or(0, |l| l.len()),
+				treasury_native_balance,
+				treasury_community_balance,
+			});
+		}
+
+		// the counter tells how many ceremonies in a row had no attested attendees
+		let mut last_attested_ceremony = None;
+		for c in (1..cindex.saturating_sub(inactivity_counter))
+			.rev()
+			.take(cycles.max(1) as usize)
+		{
+			if api.get_reputation_count((cid, c), maybe_at).await.unwrap_or(0) > 0 {
+				last_attested_ceremony = Some(c);
+				break
+			}
+		}
+
+		let completed: Vec<&CeremonyActivity> =
+			activity.iter().filter(|a| a.attested.is_some()).collect();
+		let registration_trend = match (completed.first(), completed.last()) {
+			(Some(oldest), Some(latest)) =>
+				latest.registrations as i64 - oldest.registrations as i64,
+			_ => 0,
+		};
+
+		let (oldest, latest) = (activity.first().unwrap(), activity.last().unwrap());
+		let active_locations = latest.locations;
+		let location_trend = latest.locations as i64 - oldest.locations as i64;
+		let (treasury_native_balance, treasury_community_balance) =
+			(latest.treasury_native_balance, latest.treasury_community_balance);
+		let treasury_native_balance_trend = oldest
+			.treasury_native_balance
+			.zip(latest.treasury_native_balance)
+			.map(|(o, l)| l as i128 - o as i128);
+		let treasury_community_balance_trend = oldest
+			.treasury_community_balance
+			.zip(latest.treasury_community_balance)
+			.map(|(o, l)| l - o);
+
+		let mut reasons = Vec::new();
+		if inactivity_counter > 0 {
+			reasons.push(format!(
+				"no attested attendees in the last {inactivity_counter} ceremonies, purged after {} more",
+				remaining_inactive_cycles + 1
+			));
+		}
+		if registration_trend < 0 {
+			reasons.push(format!(
+				"registrations declined by {} over {} ceremonies",
+				-registration_trend,
+				completed.len()
+			));
+		}
+		if location_trend < 0 {
+			reasons.push(format!(
+				"locations declined by {} over {} ceremonies",
+				-location_trend,
+				activity.len()
+			));
+		}
+		if treasury_community_balance_trend.is_some_and(|t| t < 0.0) {
+			reasons.push(format!(
+				"treasury community balance declined by {} over {} ceremonies",
+				-treasury_community_balance_trend.unwrap(),
+				activity.len()
+			));
+		}
+		let at_risk = inactivity_counter > 0 && remaining_inactive_cycles <= warn_cycles;
+
+		report.push(CommunityHealth {
+			cid: cid.to_string(),
+			name: names
+				.iter()
+				.find(|n| n.cid == cid)
+				.map_or(String::new(), |n| String::from_utf8_lossy(&n.name).to_string()),
+			last_attested_ceremony,
+			inactivity_counter,
+			remaining_inactive_cycles,
+			activity,
+			registration_trend,
+			active_locations,
+			location_trend,
+			treasury_account: treasury.map(|a| a.to_string()),
+			treasury_native_balance,
+			treasury_community_balance,
+			treasury_native_balance_trend,
+			treasury_community_balance_trend,
+			at_risk,
+			reasons,
+		});
+	}
+	println!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
+
+/// Free native and community currency balance of a treasury at `at`.
+async fn get_treasury_balances(
+	api: &Api,
+	cid: CommunityIdentifier,
+	account: &AccountId,
+	at: Hash,
+) -> (Balance, f64) {
+	let native = api
+		.get_storage_map::<_, AccountInfo<Nonce, AccountData<Balance>>>(
+			"System",
+			"Account",
+			account.clone(),
+			Some(at),
+		)
+		.await
+		.unwrap()
+		.map_or(0, |info| info.data.free);
+	let community = get_community_balance(api, &cid.to_string(), account, Some(at)).await;
+	(native, community.to_num::<f64>())
+}
@@ -16,7 +16,7 @@ use encointer_primitives::balances::{to_U64F64, BalanceEntry, BalanceType, Demur
 use encointer_primitives::{communities::CommunityIdentifier, fixed::transcendental::exp};
 use log::{debug, error, info};
 use pallet_transaction_payment::FeeDetails;
-use parity_scale_codec::Encode;
+use parity_scale_codec::{Decode, Encode};
 use sp_core::{crypto::Ss58Codec, sr25519 as sr25519_core, Pair};
 
 use sp_rpc::number::NumberOrHex;
@@ -195,7 +195,7 @@ pub async fn get_community_issuance(
 	}
 }
 
-async fn get_demurrage_per_block(
+pub async fn get_demurrage_per_block(
 	api: &Api,
 	cid: CommunityIdentifier,
 	maybe_at: Option<Hash>,
@@ -218,6 +218,41 @@ async fn get_demurrage_per_block(
 	}
 }
 
+/// All balance entries of a community, without demurrage applied.
+pub async fn get_community_holders(
+	api: &Api,
+	cid: CommunityIdentifier,
+	maybe_at: Option<Hash>,
+) -> Vec<(AccountId, BalanceEntry<BlockNumber>)> {
+	let key_prefix = api
+		.get_storage_double_map_key_prefix("EncointerBalances", "Balance", cid)
+		.await
+		.unwrap();
+	let page_size = 1000;
+	let mut holders = Vec::new();
+	let mut start_key = None;
+	loop {
+		let storage_keys = api
+			.get_storage_keys_paged(Some(key_prefix.clone()), page_size, start_key, maybe_at)
+			.await
+			.unwrap();
+		for storage_key in storage_keys.iter() {
+			let key_postfix = storage_key.as_ref();
+			let account =
+				AccountId::decode(&mut key_postfix[key_postfix.len() - 32..].as_ref()).unwrap();
+			let entry: BalanceEntry<BlockNumber> =
+				api.get_storage_by_key(storage_key.clone(), maybe_at).await.unwrap().unwrap();
+			holders.push((account, entry));
+		}
+		debug!("fetched {} balance entries of {}", holders.len(), cid);
+		if storage_keys.len() < page_size as usize {
+			break
+		}
+		start_key = storage_keys.last().cloned();
+	}
+	holders
+}
+
 async fn get_all_balances(
 	api: &Api,
 	account_id: &AccountId,
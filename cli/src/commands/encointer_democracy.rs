@@ -1,7 +1,15 @@
-use crate::cli::Cli;
+use crate::cli::{AssetArgs, Cli};
 
 use crate::{
-	community_spec::demurrage_per_block_from_halving_blocks,
+	commands::{
+		encointer_ceremonies::get_reputation_history,
+		encointer_scheduler::{format_moment, get_upcoming_phases},
+	},
+	community_spec::{
+		demurrage_per_block_from_halving_blocks, halving_blocks_from_demurrage_per_block,
+		read_community_spec_from_file, CommunitySpec,
+	},
+	exit_code,
 	utils::{
 		ensure_payment, get_chain_api,
 		keys::{get_accountid_from_str, get_pair_from_str},
@@ -10,23 +18,30 @@ use crate::{
 use chrono::{prelude::*, Utc};
 use encointer_api_client_extension::{
 	set_api_extrisic_params_builder, Api, CeremoniesApi, CommunitiesApi, DemocracyApi, EncointerXt,
-	Moment, ParentchainExtrinsicSigner, SchedulerApi,
+	Moment, ParentchainExtrinsicSigner, ReputationCommitmentsApi, SchedulerApi,
 };
 use encointer_node_runtime::{AccountId, Balance, Hash};
 use encointer_primitives::{
 	balances::BalanceType,
-	ceremonies::{CeremonyIndexType, CommunityCeremony, ReputationCountType},
+	ceremonies::{CeremonyIndexType, CommunityCeremony, Reputation, ReputationCountType},
 	common::{FromStr, PalletString},
-	communities::CommunityIdentifier,
+	communities::{CommunityIdentifier, CommunityMetadata, Degree, Location},
 	democracy::{ProposalAccessPolicy, ProposalIdType, ProposalState, ReputationVec, Vote},
+	fixed::{transcendental::sqrt, types::U64F64},
+	scheduler::CeremonyPhaseType,
 	treasuries::{SwapAssetOption, SwapNativeOption},
 };
 use log::{debug, error};
 use parity_scale_codec::{Decode, Encode};
-use sp_core::{sr25519 as sr25519_core, ConstU32};
+use sp_core::{sr25519 as sr25519_core, ConstU32, Pair};
 use substrate_api_client::{
 	ac_compose_macros::compose_extrinsic, GetStorage, SubmitAndWatch, XtStatus,
 };
+use xcm::v5::{
+	AssetId,
+	Junction::{GeneralIndex, PalletInstance, Parachain},
+	Location as XcmV5Location,
+};
 
 // Some type aliases
 pub use encointer_node_runtime::VersionedLocatableAsset as XcmLocation;
@@ -38,50 +53,122 @@ pub type Proposal =
 pub type ProposalAction =
 	encointer_primitives::democracy::ProposalAction<AccountId, Balance, Moment, XcmLocation>;
 
-pub async fn submit_set_inactivity_timeout_proposal(
-	cli: &Cli,
-	account: &str,
-	inactivity_timeout: u32,
-) {
+async fn submit_proposal(cli: &Cli, mut api: Api, account: &str, action: ProposalAction) {
 	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
 	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
 	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
 	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
 
-	let xt: EncointerXt<_> = compose_extrinsic!(
-		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::SetInactivityTimeout(inactivity_timeout)
-	)
-	.unwrap();
+	let xt: EncointerXt<_> =
+		compose_extrinsic!(api, "EncointerDemocracy", "submit_proposal", action.clone()).unwrap();
 	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
 	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!("Proposal Submitted: Set inactivity timeout to {inactivity_timeout:?}");
+	println!("Proposal Submitted: {}", format_proposal_action(&action));
 }
 
-pub async fn submit_update_nominal_income_proposal(cli: &Cli, account: &str, nominal_income: f64) {
-	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
-	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
-	let cid = api
-		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
+async fn proposal_cid(cli: &Cli, api: &Api) -> CommunityIdentifier {
+	api.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
+		.await
+}
+
+async fn maybe_proposal_cid(cli: &Cli, api: &Api) -> Option<CommunityIdentifier> {
+	match cli.cid.as_deref() {
+		Some(_) => Some(proposal_cid(cli, api).await),
+		None => None,
+	}
+}
+
+/// Decodes a hex encoded `VersionedLocatableAsset`.
+fn decode_asset_id(asset_id_hex: &str) -> XcmLocation {
+	let asset_id_bytes = hex::decode(asset_id_hex.strip_prefix("0x").unwrap_or(asset_id_hex))
+		.expect("asset-id must be valid hex");
+	Decode::decode(&mut asset_id_bytes.as_slice()).expect("invalid asset-id encoding")
+}
+
+/// Builds the `VersionedLocatableAsset` of an asset held on a sibling chain, unless it is given
+/// encoded with `--asset-id`.
+fn locatable_asset(asset: &AssetArgs) -> XcmLocation {
+	if let Some(asset_id_hex) = asset.asset_id.as_deref() {
+		return decode_asset_id(asset_id_hex)
+	}
+	let location = XcmV5Location::new(1, [Parachain(asset.para_id)]);
+	let asset_id = AssetId(match asset.general_index {
+		Some(index) =>
+			XcmV5Location::new(0, [PalletInstance(asset.pallet_instance), GeneralIndex(index)]),
+		None => XcmV5Location::parent(),
+	});
+	match asset.xcm_version {
+		5 => XcmLocation::V5 { location, asset_id },
+		4 => XcmLocation::V4 {
+			location: location.try_into().expect("location not representable in XCM v4"),
+			asset_id: asset_id.try_into().expect("asset id not representable in XCM v4"),
+		},
+		3 => {
+			let location: xcm::v4::Location =
+				location.try_into().expect("location not representable in XCM v4");
+			let asset_id: xcm::v4::AssetId =
+				asset_id.try_into().expect("asset id not representable in XCM v4");
+			XcmLocation::V3 {
+				location: location.try_into().expect("location not representable in XCM v3"),
+				asset_id: asset_id.try_into().expect("asset id not representable in XCM v3"),
+			}
+		},
+		v => panic!("unsupported XCM version {v}, expected 3, 4 or 5"),
+	}
+}
+
+pub async fn submit_add_location_proposal(cli: &Cli, account: &str, lat: f64, lon: f64) {
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
+	let location = Location { lat: Degree::from_num(lat), lon: Degree::from_num(lon) };
+	submit_proposal(cli, api, account, ProposalAction::AddLocation(cid, location)).await;
+}
+
+pub async fn submit_remove_location_proposal(cli: &Cli, account: &str, lat: f64, lon: f64) {
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
+	let location = Location { lat: Degree::from_num(lat), lon: Degree::from_num(lon) };
+	submit_proposal(cli, api, account, ProposalAction::RemoveLocation(cid, location)).await;
+}
+
+pub async fn submit_update_community_metadata_proposal(cli: &Cli, account: &str, specfile: &str) {
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
+	let metadata = read_community_spec_from_file(specfile).metadata();
+	submit_proposal(cli, api, account, ProposalAction::UpdateCommunityMetadata(cid, metadata))
 		.await;
-	let new_income = BalanceType::from_num(nominal_income);
-	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
-	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
+}
 
-	let xt: EncointerXt<_> = compose_extrinsic!(
-		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::UpdateNominalIncome(cid, new_income)
-	)
-	.unwrap();
-	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
-	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!("Proposal Submitted: Update nominal income for cid {cid} to {new_income}");
+pub async fn submit_spend_asset_proposal(
+	cli: &Cli,
+	account: &str,
+	arg_to: &str,
+	amount: u128,
+	asset: &AssetArgs,
+) {
+	let api = get_chain_api(cli).await;
+	let maybecid = maybe_proposal_cid(cli, &api).await;
+	let to = get_accountid_from_str(arg_to);
+	let asset_id = locatable_asset(asset);
+	submit_proposal(cli, api, account, ProposalAction::SpendAsset(maybecid, to, amount, asset_id))
+		.await;
+}
+
+pub async fn submit_set_inactivity_timeout_proposal(
+	cli: &Cli,
+	account: &str,
+	inactivity_timeout: u32,
+) {
+	let api = get_chain_api(cli).await;
+	submit_proposal(cli, api, account, ProposalAction::SetInactivityTimeout(inactivity_timeout))
+		.await;
+}
+
+pub async fn submit_update_nominal_income_proposal(cli: &Cli, account: &str, nominal_income: f64) {
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
+	let new_income = BalanceType::from_num(nominal_income);
+	submit_proposal(cli, api, account, ProposalAction::UpdateNominalIncome(cid, new_income)).await;
 }
 
 pub async fn submit_update_demurrage_proposal(
@@ -89,77 +176,31 @@ pub async fn submit_update_demurrage_proposal(
 	account: &str,
 	demurrage_halving_blocks: u64,
 ) {
-	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
-	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
-	let cid = api
-		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
-		.await;
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
 	let new_demurrage_per_block = demurrage_per_block_from_halving_blocks(demurrage_halving_blocks);
-	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
-	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
-
-	let xt: EncointerXt<_> = compose_extrinsic!(
+	submit_proposal(
+		cli,
 		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::UpdateDemurrage(cid, new_demurrage_per_block)
+		account,
+		ProposalAction::UpdateDemurrage(cid, new_demurrage_per_block),
 	)
-	.unwrap();
-	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
-	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!("Proposal Submitted: Update demurrage for cid {cid} to {new_demurrage_per_block}");
+	.await;
 }
 
 pub async fn submit_petition(cli: &Cli, account: &str, demand_str: &str) {
-	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
-	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
-	let maybecid = if let Some(cid) = cli.cid.as_deref() {
-		Some(api.verify_cid(cid, None).await)
-	} else {
-		None
-	};
+	let api = get_chain_api(cli).await;
+	let maybecid = maybe_proposal_cid(cli, &api).await;
 	let demand =
 		PalletString::from_str(demand_str).expect("Petition demand too long. must be < 256 chars");
-	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
-	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
-
-	let xt: EncointerXt<_> = compose_extrinsic!(
-		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::Petition(maybecid, demand.clone())
-	)
-	.unwrap();
-	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
-	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!("Proposal Submitted: Petition for cid {maybecid:?} demanding: {demand_str}");
+	submit_proposal(cli, api, account, ProposalAction::Petition(maybecid, demand)).await;
 }
 
 pub async fn submit_spend_native_proposal(cli: &Cli, account: &str, arg_to: &str, amount: u128) {
-	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
-	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
-	let maybecid = if let Some(cid) = cli.cid.as_deref() {
-		Some(api.verify_cid(cid, None).await)
-	} else {
-		None
-	};
+	let api = get_chain_api(cli).await;
+	let maybecid = maybe_proposal_cid(cli, &api).await;
 	let to = get_accountid_from_str(arg_to);
-	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
-	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
-
-	let xt: EncointerXt<_> = compose_extrinsic!(
-		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::SpendNative(maybecid, to.clone(), amount)
-	)
-	.unwrap();
-	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
-	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!("Proposal Submitted: Spend Native for cid {maybecid:?} to {to}, amount {amount}");
+	submit_proposal(cli, api, account, ProposalAction::SpendNative(maybecid, to, amount)).await;
 }
 
 pub async fn submit_issue_swap_native_option_proposal(
@@ -172,79 +213,40 @@ pub async fn submit_issue_swap_native_option_proposal(
 	valid_from: Option<Moment>,
 	valid_until: Option<Moment>,
 ) {
-	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
-	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
-	let cid = api
-		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
-		.await;
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
 	let to = get_accountid_from_str(arg_to);
 	let rate = rate.map(|v| BalanceType::from_num(v));
-	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
-	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
-
 	let option = SwapNativeOption { cid, native_allowance, rate, do_burn, valid_from, valid_until };
-	let xt: EncointerXt<_> = compose_extrinsic!(
-		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::IssueSwapNativeOption(cid, to.clone(), option)
-	)
-	.unwrap();
-	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
-	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!(
-		"Proposal Submitted: Issue SwapNativeOption for {cid} to {to}, allowance={native_allowance}"
-	);
+	submit_proposal(cli, api, account, ProposalAction::IssueSwapNativeOption(cid, to, option))
+		.await;
 }
 
 pub async fn submit_issue_swap_asset_option_proposal(
 	cli: &Cli,
 	account: &str,
 	arg_to: &str,
-	asset_id_hex: &str,
+	asset: &AssetArgs,
 	asset_allowance: u128,
 	rate: Option<f64>,
 	do_burn: bool,
 	valid_from: Option<Moment>,
 	valid_until: Option<Moment>,
 ) {
-	let who = get_pair_from_str(account);
-	let mut api = get_chain_api(cli).await;
-	api.set_signer(ParentchainExtrinsicSigner::new(sr25519_core::Pair::from(who.clone())));
-	let cid = api
-		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), None)
-		.await;
+	let api = get_chain_api(cli).await;
+	let cid = proposal_cid(cli, &api).await;
 	let to = get_accountid_from_str(arg_to);
 	let rate = rate.map(|v| BalanceType::from_num(v));
-	let asset_id_bytes = hex::decode(asset_id_hex.strip_prefix("0x").unwrap_or(asset_id_hex))
-		.expect("asset-id must be valid hex");
-	let asset_id: XcmLocation =
-		Decode::decode(&mut asset_id_bytes.as_slice()).expect("invalid asset-id encoding");
-	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
-	set_api_extrisic_params_builder(&mut api, tx_payment_cid_arg).await;
-
 	let option = SwapAssetOption {
 		cid,
-		asset_id: asset_id.clone(),
+		asset_id: locatable_asset(asset),
 		asset_allowance,
 		rate,
 		do_burn,
 		valid_from,
 		valid_until,
 	};
-	let xt: EncointerXt<_> = compose_extrinsic!(
-		api,
-		"EncointerDemocracy",
-		"submit_proposal",
-		ProposalAction::IssueSwapAssetOption(cid, to.clone(), option)
-	)
-	.unwrap();
-	ensure_payment(&api, &xt.encode().into(), tx_payment_cid_arg).await;
-	let _result = api.submit_and_watch_extrinsic_until(xt, XtStatus::InBlock).await;
-	println!(
-		"Proposal Submitted: Issue SwapAssetOption for {cid} to {to}, allowance={asset_allowance}"
-	);
+	submit_proposal(cli, api, account, ProposalAction::IssueSwapAssetOption(cid, to, option)).await;
 }
 
 pub async fn list_proposals(cli: &Cli, all: bool) {
@@ -304,26 +306,7 @@ pub async fn list_proposals(cli: &Cli, all: bool) {
 			"Proposal id: {} (reputation commitment purpose id: {})",
 			*proposal_id, purpose_id
 		);
-		let proposal_str = match &proposal.action {
-			ProposalAction::SetInactivityTimeout(timeout) =>
-				format!("Set inactivity timeout to {timeout}"),
-			ProposalAction::UpdateNominalIncome(cid, income) =>
-				format!("Update nominal income for {cid} to {income}"),
-			ProposalAction::Petition(maybecid, demand) => format!(
-				"Petition for {} demanding: {}",
-				cid_or_global(maybecid),
-				String::from_utf8_lossy(demand)
-			),
-			ProposalAction::SpendNative(maybecid, to, amount) => format!(
-				"Spend Native from {} treasury to {to}, amount {amount}",
-				cid_or_global(maybecid)
-			),
-			ProposalAction::IssueSwapNativeOption(cid, to, opt) =>
-				super::encointer_treasuries::format_swap_native_option(cid, to, opt),
-			ProposalAction::IssueSwapAssetOption(cid, to, opt) =>
-				super::encointer_treasuries::format_swap_asset_option(cid, to, opt),
-			_ => format!("{:?}", proposal.action),
-		};
+		let proposal_str = format_proposal_action(&proposal.action);
 		println!("🛠 action: {:?}", proposal_str);
 		println!("▶️ started at: {}", start.format("%Y-%m-%d %H:%M:%S %Z").to_string());
 		println!(
@@ -386,7 +369,9 @@ pub async fn vote(
 	account: &str,
 	proposal_id: u128,
 	vote_raw: &str,
-	reputation_vec_str: &str,
+	reputation_vec_str: Option<&str>,
+	auto_reputation: bool,
+	dryrun: bool,
 ) {
 	let who = get_pair_from_str(account);
 	let mut api = get_chain_api(cli).await;
@@ -396,8 +381,25 @@ pub async fn vote(
 		"nay" => Vote::Nay,
 		_ => panic!("invalid vote"),
 	};
-	let rep_parts: Vec<&str> = reputation_vec_str.split(",").collect();
-	let reputation_vec: Vec<CommunityCeremony> =
+	let state = get_proposal(&api, proposal_id, cli.at_block()).await.state;
+	if !state.can_update() {
+		error!("proposal {proposal_id} is {state:?}, voting is closed");
+		std::process::exit(exit_code::PROPOSAL_CLOSED);
+	}
+	let reputation_vec: Vec<CommunityCeremony> = if auto_reputation {
+		let account_id: AccountId = sr25519_core::Pair::from(who.clone()).public().into();
+		let reputations =
+			get_eligible_reputations(&api, &account_id, proposal_id, cli.at_block()).await;
+		if reputations.is_empty() {
+			error!("no eligible reputation left to vote on proposal {proposal_id}");
+			std::process::exit(exit_code::INVALID_REPUTATION);
+		}
+		reputations
+	} else {
+		let rep_parts: Vec<&str> = reputation_vec_str
+			.expect("please supply reputations or --auto-reputation")
+			.split(",")
+			.collect();
 		futures::future::join_all(rep_parts.into_iter().map(|rep| {
 			let api_local = api.clone();
 			async move {
@@ -408,7 +410,20 @@ pub async fn vote(
 				)
 			}
 		}))
-		.await;
+		.await
+	};
+	println!(
+		"voting {vote_raw} on proposal {proposal_id} with weight {}: {}",
+		reputation_vec.len(),
+		reputation_vec
+			.iter()
+			.map(|(cid, c)| format!("{cid}_{c}"))
+			.collect::<Vec<_>>()
+			.join(",")
+	);
+	if dryrun {
+		return
+	}
 	let reputation_bvec = ReputationVec::<ConstU32<1024>>::try_from(reputation_vec).unwrap();
 
 	let tx_payment_cid_arg = cli.tx_payment_cid.as_deref();
@@ -421,6 +436,59 @@ pub async fn vote(
 	println!("Vote submitted: {vote_raw:?} for proposal {proposal_id:?}");
 }
 
+/// All verified reputations of `account` which count for the proposal and haven't been committed
+/// for its purpose yet, i.e. the largest set which the pallet accepts in a vote.
+async fn get_eligible_reputations(
+	api: &Api,
+	account: &AccountId,
+	proposal_id: ProposalIdType,
+	maybe_at: Option<Hash>,
+) -> Vec<CommunityCeremony> {
+	let proposal = get_proposal(api, proposal_id, maybe_at).await;
+	let purpose_id = api
+		.get_purpose_id(proposal_id, maybe_at)
+		.await
+		.unwrap()
+		.expect("proposal has no reputation commitment purpose");
+	let cindexes = get_relevant_cindexes(api, proposal.start_cindex, maybe_at).await;
+	let policy = proposal.action.get_access_policy();
+	let history = match get_reputation_history(api, account, maybe_at).await {
+		Some(history) => history,
+		None => {
+			error!("could not fetch reputation over rpc");
+			std::process::exit(exit_code::RPC_ERROR);
+		},
+	};
+
+	let mut eligible = Vec::new();
+	for (cindex, rep) in history {
+		let cid = rep.community_identifier;
+		if !matches!(rep.reputation, Reputation::VerifiedUnlinked | Reputation::VerifiedLinked(_)) ||
+			!cindexes.contains(&cindex)
+		{
+			continue
+		}
+		if let ProposalAccessPolicy::Community(policy_cid) = policy {
+			if policy_cid != cid {
+				continue
+			}
+		}
+		if api
+			.get_commitment(&(cid, cindex), (purpose_id, account.clone()), maybe_at)
+			.await
+			.unwrap()
+			.is_some()
+		{
+			debug!("reputation {}_{} already committed for purpose {}", cid, cindex, purpose_id);
+			continue
+		}
+		if !eligible.contains(&(cid, cindex)) {
+			eligible.push((cid, cindex));
+		}
+	}
+	eligible
+}
+
 pub async fn update_proposal_state(cli: &Cli, account: &str, proposal_id: u128) {
 	let who = get_pair_from_str(account);
 	let mut api = get_chain_api(cli).await;
@@ -435,6 +503,192 @@ pub async fn update_proposal_state(cli: &Cli, account: &str, proposal_id: u128)
 	println!("Proposal state updated for proposal {proposal_id:?}");
 }
 
+async fn get_proposal(api: &Api, proposal_id: ProposalIdType, maybe_at: Option<Hash>) -> Proposal {
+	api.get_storage_map("EncointerDemocracy", "Proposals", proposal_id, maybe_at)
+		.await
+		.unwrap()
+		.unwrap_or_else(|| panic!("proposal {proposal_id} not found"))
+}
+
+/// Forecasts the outcome of a proposal under adaptive quorum biasing.
+///
+/// `ayes`, `turnout` and `electorate` replace the on-chain values for a what-if analysis.
+pub async fn forecast_proposal(
+	cli: &Cli,
+	proposal_id: ProposalIdType,
+	ayes: Option<u128>,
+	turnout: Option<u128>,
+	electorate: Option<u128>,
+) {
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let proposal = get_proposal(&api, proposal_id, maybe_at).await;
+	let confirmation_period = api.get_confirmation_period().await.unwrap().as_millis() as Moment;
+	let proposal_lifetime = api.get_proposal_lifetime().await.unwrap().as_millis() as Moment;
+	let min_turnout_permill = api.get_min_turnout().await.unwrap();
+	let now: Moment = api.get_storage("Timestamp", "Now", maybe_at).await.unwrap().unwrap();
+
+	let tally = api.get_tally(proposal_id, maybe_at).await.unwrap().unwrap_or_default();
+	let what_if = ayes.is_some() || turnout.is_some() || electorate.is_some();
+	// the pallet fixes the electorate when the proposal is submitted
+	let electorate = electorate.unwrap_or(proposal.electorate_size as u128);
+	let turnout = turnout.unwrap_or(tally.turnout);
+	let ayes = ayes.unwrap_or(tally.ayes);
+	if ayes > turnout || turnout > electorate {
+		panic!("ayes {ayes} must not exceed turnout {turnout}, which must not exceed electorate {electorate}");
+	}
+
+	println!("Proposal id: {proposal_id}{}", if what_if { " (what-if)" } else { "" });
+	println!("🛠 action: {}", format_proposal_action(&proposal.action));
+	println!("state: {:?}", proposal.state);
+	println!("👥 electorate: {electorate}");
+	println!(
+		"🗳 turnout: {turnout} votes = {:.3}% of electorate, ayes: {ayes}, nays: {}",
+		percent(turnout, electorate),
+		turnout - ayes
+	);
+	let passing = is_passing(electorate, turnout, ayes, min_turnout_permill);
+	println!("{}", if passing { "✅ passing" } else { "❌ not passing" });
+	match additional_ayes_needed(electorate, turnout, ayes, min_turnout_permill) {
+		Some(0) => (),
+		Some(n) =>
+			println!("➕ {n} more aye votes needed to pass if no further nay votes are cast"),
+		None => println!("➕ can't pass anymore, even if all remaining voters vote aye"),
+	}
+
+	println!("turnout, turnout %, AQB approval threshold %, ayes needed, ayes missing");
+	let mut turnouts: Vec<u128> = [0, 100, 250, 500, 750, 1000]
+		.iter()
+		.map(|permill| (electorate * permill).div_ceil(1000))
+		.chain([turnout, (electorate * min_turnout_permill).div_ceil(1000)])
+		.filter(|t| *t >= turnout.max(1))
+		.collect();
+	turnouts.sort();
+	turnouts.dedup();
+	for t in turnouts {
+		let needed = ayes_needed(electorate, t, min_turnout_permill);
+		println!(
+			"{t}, {:.3}, {:.3}, {}, {}",
+			percent(t, electorate),
+			approval_threshold_percent(electorate, t),
+			needed.map_or("-".into(), |n| n.to_string()),
+			needed.map_or("-".into(), |n| n.saturating_sub(ayes).to_string())
+		);
+	}
+
+	let expiry = proposal.start + proposal_lifetime;
+	let approval = match proposal.state {
+		ProposalState::Ongoing => {
+			println!(
+				"⏳ expires in {} at {}",
+				format_millis(expiry.saturating_sub(now)),
+				format_moment(expiry)
+			);
+			// the state is only updated on votes or `update_proposal_state`
+			passing.then_some(now.max(proposal.start) + confirmation_period)
+		},
+		ProposalState::Confirming { since } => {
+			if !passing {
+				println!("⚠️ confirmation is cancelled on the next state update");
+			}
+			passing.then_some(since + confirmation_period)
+		},
+		_ => {
+			println!("state is final");
+			None
+		},
+	};
+	if let Some(approval) = approval {
+		println!("👍 earliest approval after confirmation period: {}", format_moment(approval));
+		let cycle_duration = api.get_cycle_duration(maybe_at).await.unwrap();
+		let cycles = (approval.saturating_sub(now) / cycle_duration) as u32 + 1;
+		if let Some(enactment) = get_upcoming_phases(&api, cycles, maybe_at)
+			.await
+			.into_iter()
+			.find(|p| p.phase == CeremonyPhaseType::Assigning && p.start >= approval)
+		{
+			println!(
+				"🏛 earliest enactment at start of assigning phase of cycle {}: {}",
+				enactment.cindex,
+				format_moment(enactment.start)
+			);
+		}
+	}
+}
+
+/// Mirrors `is_passing` of the democracy pallet, which fails with `AQBError` where this returns
+/// false, e.g. for zero turnout.
+fn is_passing(electorate: u128, turnout: u128, ayes: u128, min_turnout_permill: u128) -> bool {
+	let turnout_permill = (turnout * 1000).checked_div(electorate).unwrap_or(0);
+	if turnout_permill < min_turnout_permill {
+		return false
+	}
+	positive_turnout_bias(electorate, turnout, ayes).unwrap_or(false)
+}
+
+/// Adaptive quorum biasing in the fixed point arithmetic of the democracy pallet:
+/// approved if `ayes > sqrt(e) * sqrt(t) / (sqrt(e) / sqrt(t) + 1)`.
+fn positive_turnout_bias(electorate: u128, turnout: u128, ayes: u128) -> Option<bool> {
+	let sqrt_e = sqrt::<U64F64, U64F64>(U64F64::from_num(electorate)).ok()?;
+	let sqrt_t = sqrt::<U64F64, U64F64>(U64F64::from_num(turnout)).ok()?;
+	let approval_threshold = sqrt_e.checked_mul(sqrt_t).and_then(|r| {
+		r.checked_div(sqrt_e.checked_div(sqrt_t).and_then(|r| r.checked_add(1u32.into()))?)
+	})?;
+	Some(U64F64::from_num(ayes) > approval_threshold)
+}
+
+/// Least number of ayes needed to pass at a given turnout.
+fn ayes_needed(electorate: u128, turnout: u128, min_turnout_permill: u128) -> Option<u128> {
+	let threshold = approval_threshold_percent(electorate, turnout) / 100f64;
+	// start close to the threshold and correct rounding errors
+	let mut ayes = ((turnout as f64 * threshold).floor() as u128).min(turnout);
+	while ayes > 0 && is_passing(electorate, turnout, ayes - 1, min_turnout_permill) {
+		ayes -= 1;
+	}
+	while !is_passing(electorate, turnout, ayes, min_turnout_permill) {
+		if ayes == turnout {
+			return None
+		}
+		ayes += 1;
+	}
+	Some(ayes)
+}
+
+/// Number of additional aye votes needed to pass if no more nays are cast.
+fn additional_ayes_needed(
+	electorate: u128,
+	turnout: u128,
+	ayes: u128,
+	min_turnout_permill: u128,
+) -> Option<u128> {
+	let passes =
+		|more: u128| is_passing(electorate, turnout + more, ayes + more, min_turnout_permill);
+	let remaining = electorate.saturating_sub(turnout);
+	if !passes(remaining) {
+		return None
+	}
+	// approval only grows with more ayes, so bisect
+	let (mut low, mut high) = (0, remaining);
+	while low < high {
+		let mid = low + (high - low) / 2;
+		if passes(mid) {
+			high = mid;
+		} else {
+			low = mid + 1;
+		}
+	}
+	Some(low)
+}
+
+fn percent(part: u128, total: u128) -> f64 {
+	100f64 * part as f64 / total.max(1) as f64
+}
+
+fn format_millis(millis: Moment) -> String {
+	let minutes = millis / 60_000;
+	format!("{}d {}h {}m", minutes / 1440, minutes / 60 % 24, minutes % 60)
+}
+
 /// count reputation assuming we would start
 async fn get_relevant_electorate(
 	api: &Api,
@@ -442,6 +696,25 @@ async fn get_relevant_electorate(
 	scope: ProposalAccessPolicy,
 	maybe_at: Option<Hash>,
 ) -> ReputationCountType {
+	let mut count: ReputationCountType = 0;
+	for c in get_relevant_cindexes(api, proposal_start_cindex, maybe_at).await {
+		count += match scope {
+			ProposalAccessPolicy::Community(cid) =>
+				api.get_reputation_count((cid, c), maybe_at).await.unwrap_or(0),
+			ProposalAccessPolicy::Global =>
+				api.get_global_reputation_count(c, maybe_at).await.unwrap_or(0),
+		};
+	}
+	count
+}
+
+/// Ceremony indexes whose reputation may be used to vote on a proposal started at
+/// `proposal_start_cindex`.
+async fn get_relevant_cindexes(
+	api: &Api,
+	proposal_start_cindex: CeremonyIndexType,
+	maybe_at: Option<Hash>,
+) -> Vec<CeremonyIndexType> {
 	if let Ok((reputation_lifetime, cycle_duration, proposal_lifetime)) = tokio::try_join!(
 		api.get_reputation_lifetime(maybe_at),
 		api.get_cycle_duration(maybe_at),
@@ -449,26 +722,73 @@ async fn get_relevant_electorate(
 	) {
 		let proposal_lifetime_cycles =
 			u32::try_from(proposal_lifetime.as_millis().div_ceil(cycle_duration as u128)).unwrap();
-		let relevant_cindexes = (proposal_start_cindex
+		(proposal_start_cindex
 			.saturating_sub(reputation_lifetime)
-			.saturating_add(proposal_lifetime_cycles)..=
-			proposal_start_cindex.saturating_sub(2u32))
-			.collect::<Vec<CeremonyIndexType>>();
-		let mut count: ReputationCountType = 0;
-		for c in relevant_cindexes {
-			count += match scope {
-				ProposalAccessPolicy::Community(cid) =>
-					api.get_reputation_count((cid, c), maybe_at).await.unwrap_or(0),
-				ProposalAccessPolicy::Global =>
-					api.get_global_reputation_count(c, maybe_at).await.unwrap_or(0),
-			};
-		}
-		return count;
+			.saturating_add(proposal_lifetime_cycles)..=proposal_start_cindex.saturating_sub(2u32))
+			.collect()
 	} else {
 		panic!("couldn't fetch some values")
 	}
 }
 
+/// Human readable description of a proposal action.
+pub fn format_proposal_action(action: &ProposalAction) -> String {
+	match action {
+		ProposalAction::AddLocation(cid, location) =>
+			format!("Add location {} to {cid}", format_location(location)),
+		ProposalAction::RemoveLocation(cid, location) =>
+			format!("Remove location {} from {cid}", format_location(location)),
+		ProposalAction::UpdateCommunityMetadata(cid, metadata) =>
+			format!("Update metadata of {cid} to {}", format_community_metadata(metadata)),
+		ProposalAction::UpdateDemurrage(cid, demurrage) =>
+			match halving_blocks_from_demurrage_per_block(*demurrage) {
+				Some(blocks) => format!(
+					"Update demurrage for {cid} to {demurrage} per block (halving after {blocks} blocks)"
+				),
+				None => format!("Update demurrage for {cid} to {demurrage} per block"),
+			},
+		ProposalAction::UpdateNominalIncome(cid, income) =>
+			format!("Update nominal income for {cid} to {income}"),
+		ProposalAction::SetInactivityTimeout(timeout) =>
+			format!("Set inactivity timeout to {timeout}"),
+		ProposalAction::Petition(maybecid, demand) => format!(
+			"Petition for {} demanding: {}",
+			cid_or_global(maybecid),
+			String::from_utf8_lossy(demand)
+		),
+		ProposalAction::SpendNative(maybecid, to, amount) => format!(
+			"Spend Native from {} treasury to {to}, amount {amount}",
+			cid_or_global(maybecid)
+		),
+		ProposalAction::IssueSwapNativeOption(cid, to, opt) =>
+			super::encointer_treasuries::format_swap_native_option(cid, to, opt),
+		ProposalAction::SpendAsset(maybecid, to, amount, asset_id) => format!(
+			"Spend asset {asset_id:?} from {} treasury to {to}, amount {amount}",
+			cid_or_global(maybecid)
+		),
+		ProposalAction::IssueSwapAssetOption(cid, to, opt) =>
+			super::encointer_treasuries::format_swap_asset_option(cid, to, opt),
+	}
+}
+
+fn format_location(location: &Location) -> String {
+	format!("(lat {}, lon {})", location.lat, location.lon)
+}
+
+fn format_community_metadata(metadata: &CommunityMetadata) -> String {
+	let lossy = |b: &[u8]| String::from_utf8_lossy(b).to_string();
+	format!(
+		"name={}, symbol={}, assets={}, theme={}, url={}, announcement signer={:?}, rules={:?}",
+		lossy(&metadata.name),
+		lossy(&metadata.symbol),
+		lossy(&metadata.assets),
+		metadata.theme.as_ref().map_or("none".into(), |t| lossy(t)),
+		metadata.url.as_ref().map_or("none".into(), |u| lossy(u)),
+		metadata.announcement_signer,
+		metadata.rules
+	)
+}
+
 fn approval_threshold_percent(electorate: u128, turnout: u128) -> f64 {
 	100f64 / (1f64 + (turnout as f64 / electorate as f64).sqrt())
 }
@@ -479,3 +799,88 @@ fn cid_or_global(maybecid: &Option<CommunityIdentifier>) -> String {
 		None => "global".into(),
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// electorate and minimal turnout (2%) of the democracy pallet's tests
+	const ELECTORATE: u128 = 100;
+	const MIN_TURNOUT: u128 = 20;
+
+	#[test]
+	fn is_passing_matches_pallet() {
+		assert!(!is_passing(ELECTORATE, 1, 1, MIN_TURNOUT));
+		assert!(!is_passing(ELECTORATE, 10, 6, MIN_TURNOUT));
+		assert!(is_passing(ELECTORATE, 10, 9, MIN_TURNOUT));
+		assert!(is_passing(ELECTORATE, 100, 60, MIN_TURNOUT));
+		assert!(is_passing(ELECTORATE, 100, 90, MIN_TURNOUT));
+	}
+
+	#[test]
+	fn is_passing_fails_without_votes_or_electorate() {
+		assert!(!is_passing(ELECTORATE, 0, 0, 0));
+		assert!(!is_passing(0, 0, 0, 0));
+		assert!(!is_passing(0, 1, 1, MIN_TURNOUT));
+	}
+
+	#[test]
+	fn ayes_needed_is_least_passing() {
+		// threshold 10 * 10 / (10 + sqrt(10)) = 7.6
+		assert_eq!(ayes_needed(ELECTORATE, 10, MIN_TURNOUT), Some(8));
+		for turnout in 2..=ELECTORATE {
+			let ayes = ayes_needed(ELECTORATE, turnout, MIN_TURNOUT).unwrap();
+			assert!(is_passing(ELECTORATE, turnout, ayes, MIN_TURNOUT));
+			assert!(!is_passing(ELECTORATE, turnout, ayes - 1, MIN_TURNOUT));
+		}
+	}
+
+	#[test]
+	fn ayes_needed_is_none_below_min_turnout() {
+		assert_eq!(ayes_needed(ELECTORATE, 0, MIN_TURNOUT), None);
+		assert_eq!(ayes_needed(ELECTORATE, 1, MIN_TURNOUT), None);
+	}
+
+	#[test]
+	fn additional_ayes_needed_works() {
+		assert_eq!(additional_ayes_needed(ELECTORATE, 10, 9, MIN_TURNOUT), Some(0));
+		// 11 of 15 pass, 10 of 14 don't
+		assert_eq!(additional_ayes_needed(ELECTORATE, 10, 6, MIN_TURNOUT), Some(5));
+		// a second vote is needed to reach the minimal turnout
+		assert_eq!(additional_ayes_needed(ELECTORATE, 1, 1, MIN_TURNOUT), Some(1));
+		assert_eq!(additional_ayes_needed(ELECTORATE, 100, 10, MIN_TURNOUT), None);
+		// what-if turnout above the electorate
+		assert_eq!(additional_ayes_needed(ELECTORATE, 120, 10, MIN_TURNOUT), None);
+	}
+
+	fn parse_asset_args(args: &[&str]) -> Result<AssetArgs, clap::Error> {
+		use crate::cli::{Commands, DemocracyCmd, ProposeCmd};
+		use clap::Parser;
+		let cli = Cli::try_parse_from(
+			["encointer-cli", "democracy", "propose", "spend-asset", "//Alice", "//Bob", "1"]
+				.iter()
+				.chain(args),
+		)?;
+		match cli.command {
+			Commands::Democracy(DemocracyCmd::Propose(ProposeCmd::SpendAsset {
+				asset, ..
+			})) => Ok(asset),
+			_ => unreachable!(),
+		}
+	}
+
+	#[test]
+	fn encoded_asset_id_matches_typed_asset() {
+		let typed = locatable_asset(
+			&parse_asset_args(&["--asset-general-index", "1984", "--xcm-version", "4"]).unwrap(),
+		);
+		let encoded = hex::encode(typed.encode());
+		let decoded = locatable_asset(&parse_asset_args(&["--asset-id", &encoded]).unwrap());
+		assert_eq!(decoded, typed);
+	}
+
+	#[test]
+	fn encoded_asset_id_conflicts_with_typed_asset() {
+		assert!(parse_asset_args(&["--asset-id", "00", "--asset-general-index", "1984"]).is_err());
+	}
+}
@@ -0,0 +1,517 @@
+//! Long-range ceremony statistics.
+//!
+//! Ceremony registries are purged from state after some cycles, so every ceremony is queried at
+//! the last block of its cycle (found by bisection over `CurrentCeremonyIndex`). Reputation is only
+//! written when rewards are claimed, so it is queried at the end of the following cycle.
+//!
+//! Rewards are issued per meetup. The block in which a meetup was rewarded is found by bisection
+//! over `IssuedRewards`, and the `Issued` events of that block add up to the ceremony's issuance.
+//!
+//! Rows are appended to the output file as soon as they are complete. Restarting with the same
+//! file skips all (cid, cindex) pairs already present, so long ranges can be resumed. Parquet files
+//! cannot be appended to, so they are rewritten every [`PARQUET_BATCH_ROWS`] rows and at the end.
+
+use crate::{
+	cli::Cli,
+	commands::{
+		encointer_ceremonies::get_attendees_for_community_ceremony,
+		frame::{get_block_hash, get_block_number},
+	},
+	utils::{get_chain_api, OutputFormat},
+};
+use encointer_api_client_extension::{
+	Api, CeremoniesApi, CommunitiesApi, SchedulerApi, ENCOINTER_CEREMONIES,
+};
+use encointer_node_runtime::{AccountId, BalanceType, BlockNumber, Hash};
+use encointer_primitives::{
+	ceremonies::{CeremonyIndexType, MeetupIndexType, ParticipantIndexType, ReputationCountType},
+	communities::CommunityIdentifier,
+};
+use log::{info, warn};
+use parity_scale_codec::Decode;
+use parquet::{
+	data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+	file::{
+		reader::{FileReader, SerializedFileReader},
+		writer::SerializedFileWriter,
+	},
+	record::RowAccessor,
+	schema::parser::parse_message_type,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashSet},
+	fs::File,
+	io::Write,
+	str::FromStr,
+	sync::Arc,
+};
+use substrate_api_client::{FetchEvents, GetStorage};
+
+const CSV_HEADER: &str = "cid,cindex,bootstrappers,reputables,endorsees,newbies,meetups,assignees,attendees,noshows,issuance,reputation_count";
+
+/// Number of new rows after which a parquet output file is rewritten.
+const PARQUET_BATCH_ROWS: usize = 500;
+
+/// [`OutputFormat`] plus parquet, which only the history export supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryFormat {
+	Csv,
+	Json,
+	Parquet,
+}
+
+impl FromStr for HistoryFormat {
+	type Err = &'static str;
+
+	fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+		match value {
+			"parquet" => Ok(HistoryFormat::Parquet),
+			other => Ok(match OutputFormat::from_str(other)? {
+				OutputFormat::Csv => HistoryFormat::Csv,
+				OutputFormat::Json => HistoryFormat::Json,
+			}),
+		}
+	}
+}
+
+/// Same columns as `CSV_HEADER`, in the same order.
+const PARQUET_SCHEMA: &str = "
+message ceremony_history {
+	REQUIRED BINARY cid (UTF8);
+	REQUIRED INT64 cindex;
+	REQUIRED INT64 bootstrappers;
+	REQUIRED INT64 reputables;
+	REQUIRED INT64 endorsees;
+	REQUIRED INT64 newbies;
+	REQUIRED INT64 meetups;
+	REQUIRED INT64 assignees;
+	REQUIRED INT64 attendees;
+	REQUIRED INT64 noshows;
+	REQUIRED DOUBLE issuance;
+	REQUIRED INT64 reputation_count;
+}";
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CeremonyHistoryRow {
+	pub cid: String,
+	pub cindex: CeremonyIndexType,
+	pub bootstrappers: ParticipantIndexType,
+	pub reputables: ParticipantIndexType,
+	pub endorsees: ParticipantIndexType,
+	pub newbies: ParticipantIndexType,
+	pub meetups: u64,
+	pub assignees: u64,
+	pub attendees: u64,
+	pub noshows: u64,
+	/// Community currency issued as rewards for this ceremony, up to the end of the next cycle.
+	pub issuance: f64,
+	pub reputation_count: ReputationCountType,
+}
+
+impl CeremonyHistoryRow {
+	fn to_csv(&self) -> String {
+		format!(
+			"{},{},{},{},{},{},{},{},{},{},{},{}",
+			self.cid,
+			self.cindex,
+			self.bootstrappers,
+			self.reputables,
+			self.endorsees,
+			self.newbies,
+			self.meetups,
+			self.assignees,
+			self.attendees,
+			self.noshows,
+			self.issuance,
+			self.reputation_count
+		)
+	}
+
+	/// The integer column at `index` of `PARQUET_SCHEMA`.
+	fn parquet_int(&self, index: usize) -> i64 {
+		(match index {
+			1 => self.cindex as u64,
+			2 => self.bootstrappers,
+			3 => self.reputables,
+			4 => self.endorsees,
+			5 => self.newbies,
+			6 => self.meetups,
+			7 => self.assignees,
+			8 => self.attendees,
+			9 => self.noshows,
+			11 => self.reputation_count as u64,
+			_ => panic!("column {index} is not an integer column"),
+		}) as i64
+	}
+
+	fn from_parquet(row: &parquet::record::Row) -> parquet::errors::Result<Self> {
+		Ok(Self {
+			cid: row.get_string(0)?.clone(),
+			cindex: row.get_long(1)? as CeremonyIndexType,
+			bootstrappers: row.get_long(2)? as u64,
+			reputables: row.get_long(3)? as u64,
+			endorsees: row.get_long(4)? as u64,
+			newbies: row.get_long(5)? as u64,
+			meetups: row.get_long(6)? as u64,
+			assignees: row.get_long(7)? as u64,
+			attendees: row.get_long(8)? as u64,
+			noshows: row.get_long(9)? as u64,
+			issuance: row.get_double(10)?,
+			reputation_count: row.get_long(11)? as ReputationCountType,
+		})
+	}
+}
+
+fn read_parquet(path: &str) -> parquet::errors::Result<Vec<CeremonyHistoryRow>> {
+	let reader = SerializedFileReader::new(File::open(path)?)?;
+	reader
+		.get_row_iter(None)?
+		.map(|row| CeremonyHistoryRow::from_parquet(&row?))
+		.collect()
+}
+
+/// Writes all `rows` to a new parquet file, replacing `path` only once the file is complete.
+fn write_parquet(path: &str, rows: &[CeremonyHistoryRow]) -> parquet::errors::Result<()> {
+	let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+	let tmp_path = format!("{path}.tmp");
+	let mut writer =
+		SerializedFileWriter::new(File::create(&tmp_path)?, schema, Default::default())?;
+	let mut row_group = writer.next_row_group()?;
+	let mut index = 0;
+	while let Some(mut column) = row_group.next_column()? {
+		match index {
+			0 => {
+				let values: Vec<ByteArray> = rows.iter().map(|r| r.cid.as_str().into()).collect();
+				column.typed::<ByteArrayType>().write_batch(&values, None, None)?;
+			},
+			10 => {
+				let values: Vec<f64> = rows.iter().map(|r| r.issuance).collect();
+				column.typed::<DoubleType>().write_batch(&values, None, None)?;
+			},
+			_ => {
+				let values: Vec<i64> = rows.iter().map(|r| r.parquet_int(index)).collect();
+				column.typed::<Int64Type>().write_batch(&values, None, None)?;
+			},
+		}
+		column.close()?;
+		index += 1;
+	}
+	row_group.close()?;
+	writer.close()?;
+	std::fs::rename(tmp_path, path)?;
+	Ok(())
+}
+
+/// Memoizes [`last_block_of_ceremony`], as the end of a cycle is needed for several ceremonies.
+struct CeremonyEnds<'a> {
+	api: &'a Api,
+	head: BlockNumber,
+	ends: BTreeMap<CeremonyIndexType, Option<BlockNumber>>,
+}
+
+impl<'a> CeremonyEnds<'a> {
+	fn new(api: &'a Api, head: BlockNumber) -> Self {
+		Self { api, head, ends: BTreeMap::new() }
+	}
+
+	async fn get(&mut self, cindex: CeremonyIndexType) -> Option<BlockNumber> {
+		if let Some(end) = self.ends.get(&cindex) {
+			return *end
+		}
+		let end = last_block_of_ceremony(self.api, cindex, self.head).await;
+		self.ends.insert(cindex, end);
+		end
+	}
+}
+
+/// Returns the last block in which `cindex` was the current ceremony index, or `None` if the
+/// ceremony has not ended yet.
+pub async fn last_block_of_ceremony(
+	api: &Api,
+	cindex: CeremonyIndexType,
+	head: BlockNumber,
+) -> Option<BlockNumber> {
+	let cindex_at = |n: BlockNumber| async move {
+		api.get_ceremony_index(Some(get_block_hash(api, n).await)).await
+	};
+	if cindex_at(head).await <= cindex {
+		return None
+	}
+	// find the first block with a higher index
+	let (mut lo, mut hi) = (0, head);
+	while lo < hi {
+		let mid = lo + (hi - lo) / 2;
+		if cindex_at(mid).await > cindex {
+			hi = mid;
+		} else {
+			lo = mid + 1;
+		}
+	}
+	Some(lo.saturating_sub(1))
+}
+
+pub async fn get_count(
+	api: &Api,
+	count: &'static str,
+	cid: CommunityIdentifier,
+	cindex: CeremonyIndexType,
+	at: Hash,
+) -> ParticipantIndexType {
+	api.get_storage_map(ENCOINTER_CEREMONIES, count, (cid, cindex), Some(at))
+		.await
+		.unwrap()
+		.unwrap_or(0)
+}
+
+async fn is_reward_issued_at(
+	api: &Api,
+	cid: CommunityIdentifier,
+	cindex: CeremonyIndexType,
+	meetup_index: MeetupIndexType,
+	block: BlockNumber,
+) -> bool {
+	let at = get_block_hash(api, block).await;
+	api.is_reward_issued(&(cid, cindex), meetup_index, Some(at)).await.unwrap()
+}
+
+/// Sums the `Issued` events of `cid` in the extrinsics of block `at` which issued meetup rewards
+/// of `cid`. All of them belong to the same ceremony, as it only depends on the phase.
+async fn rewards_issued_in_block(api: &Api, cid: CommunityIdentifier, at: Hash) -> BalanceType {
+	let events = api.fetch_events_from_block(at).await.expect("could not fetch events");
+	let mut issued = Vec::new();
+	let mut reward_phases = Vec::new();
+	for event in events.iter().flatten() {
+		let mut fields = event.field_bytes();
+		match (event.pallet_name(), event.variant_name()) {
+			("EncointerBalances", "Issued") =>
+				match <(CommunityIdentifier, AccountId, BalanceType)>::decode(&mut fields) {
+					Ok((c, _, amount)) if c == cid => issued.push((event.phase(), amount)),
+					Ok(_) => (),
+					Err(e) => warn!("could not decode Issued: {:?}", e),
+				},
+			(ENCOINTER_CEREMONIES, "RewardsIssued") =>
+				match CommunityIdentifier::decode(&mut fields) {
+					Ok(c) if c == cid => reward_phases.push(event.phase()),
+					Ok(_) => (),
+					Err(e) => warn!("could not decode RewardsIssued: {:?}", e),
+				},
+			_ => (),
+		}
+	}
+	issued
+		.into_iter()
+		.filter(|(phase, _)| reward_phases.contains(phase))
+		.fold(BalanceType::from_num(0), |total, (_, amount)| total + amount)
+}
+
+/// Community currency issued as rewards of ceremony `cindex` in blocks `after + 1..=until`.
+async fn ceremony_issuance(
+	api: &Api,
+	cid: CommunityIdentifier,
+	cindex: CeremonyIndexType,
+	meetups: u64,
+	after: BlockNumber,
+	until: BlockNumber,
+) -> BalanceType {
+	let mut reward_blocks = BTreeSet::new();
+	for meetup_index in 1..=meetups {
+		if !is_reward_issued_at(api, cid, cindex, meetup_index, until).await {
+			continue
+		}
+		// find the first block in which the meetup's rewards were marked as issued
+		let (mut lo, mut hi) = (after + 1, until);
+		while lo < hi {
+			let mid = lo + (hi - lo) / 2;
+			if is_reward_issued_at(api, cid, cindex, meetup_index, mid).await {
+				hi = mid;
+			} else {
+				lo = mid + 1;
+			}
+		}
+		reward_blocks.insert(lo);
+	}
+	let mut issuance = BalanceType::from_num(0);
+	for block in reward_blocks {
+		issuance += rewards_issued_in_block(api, cid, get_block_hash(api, block).await).await;
+	}
+	issuance
+}
+
+/// Blocks delimiting a ceremony's data.
+struct CeremonyBlocks {
+	/// Last block of the previous cycle.
+	start: BlockNumber,
+	/// Last block of the ceremony's cycle.
+	end: BlockNumber,
+	/// Last block of the next cycle, in which rewards can still be claimed, or the head.
+	claims_end: BlockNumber,
+}
+
+async fn collect_row(
+	api: &Api,
+	cid: CommunityIdentifier,
+	cindex: CeremonyIndexType,
+	blocks: &CeremonyBlocks,
+) -> CeremonyHistoryRow {
+	let end_of_cycle = get_block_hash(api, blocks.end).await;
+	let reputation_at = get_block_hash(api, blocks.claims_end).await;
+	let cc = (cid, cindex);
+	let assignees = api
+		.get_assignment_counts(&cc, Some(end_of_cycle))
+		.await
+		.map(|c| c.get_number_of_participants())
+		.unwrap_or(0);
+	let (attendees, noshows) =
+		get_attendees_for_community_ceremony(api, cc, Some(reputation_at)).await;
+	let meetups = api.get_meetup_count(&cc, Some(end_of_cycle)).await.unwrap_or(0);
+	let issuance =
+		ceremony_issuance(api, cid, cindex, meetups, blocks.start, blocks.claims_end).await;
+	CeremonyHistoryRow {
+		cid: cid.to_string(),
+		cindex,
+		bootstrappers: get_count(api, "BootstrapperCount", cid, cindex, end_of_cycle).await,
+		reputables: get_count(api, "ReputableCount", cid, cindex, end_of_cycle).await,
+		endorsees: get_count(api, "EndorseeCount", cid, cindex, end_of_cycle).await,
+		newbies: get_count(api, "NewbieCount", cid, cindex, end_of_cycle).await,
+		meetups,
+		assignees,
+		attendees: attendees.len() as u64,
+		noshows: noshows.len() as u64,
+		issuance: issuance.to_num::<f64>(),
+		reputation_count: api.get_reputation_count(cc, Some(reputation_at)).await.unwrap_or(0),
+	}
+}
+
+/// Reads the (cid, cindex) pairs already present in a csv or json output file.
+fn completed_rows(path: &str, format: HistoryFormat) -> HashSet<(String, CeremonyIndexType)> {
+	let Ok(content) = std::fs::read_to_string(path) else { return HashSet::new() };
+	content
+		.lines()
+		.filter(|l| !l.trim().is_empty() && *l != CSV_HEADER)
+		.filter_map(|l| match format {
+			HistoryFormat::Csv => {
+				let mut fields = l.split(',');
+				Some((fields.next()?.to_string(), fields.next()?.parse().ok()?))
+			},
+			HistoryFormat::Json =>
+				serde_json::from_str::<CeremonyHistoryRow>(l).ok().map(|r| (r.cid, r.cindex)),
+			HistoryFormat::Parquet => None,
+		})
+		.collect()
+}
+
+pub async fn export_ceremony_history(
+	cli: &Cli,
+	from: CeremonyIndexType,
+	to: Option<CeremonyIndexType>,
+	all_communities: bool,
+	format: &str,
+	out: Option<&str>,
+) {
+	let format = HistoryFormat::from_str(format).unwrap();
+	let api = get_chain_api(cli).await;
+	let head = get_block_number(&api, cli.at_block()).await;
+	let head_hash = get_block_hash(&api, head).await;
+	let to = to.unwrap_or(api.get_ceremony_index(Some(head_hash)).await - 1);
+
+	// with --all-communities, the communities are read per ceremony, as some may have been purged
+	let only_cid =
+		(!all_communities).then(|| cli.cid.as_deref().expect("please supply argument --cid"));
+	let only_cid = match only_cid {
+		Some(cid) => Some(api.verify_cid(cid, None).await),
+		None => None,
+	};
+
+	let parquet_out = match (format, out) {
+		(HistoryFormat::Parquet, Some(path)) => Some(path),
+		(HistoryFormat::Parquet, None) => panic!("--format parquet requires --out"),
+		_ => None,
+	};
+	let mut parquet_rows = match parquet_out {
+		Some(path) if std::path::Path::new(path).exists() =>
+			read_parquet(path).expect("could not read existing parquet file"),
+		_ => vec![],
+	};
+	let mut unwritten_rows = 0;
+	let done: HashSet<(String, CeremonyIndexType)> = match (format, out) {
+		(HistoryFormat::Parquet, _) =>
+			parquet_rows.iter().map(|r| (r.cid.clone(), r.cindex)).collect(),
+		(_, Some(path)) => completed_rows(path, format),
+		(_, None) => HashSet::new(),
+	};
+	let mut writer: Box<dyn Write> = match out {
+		Some(_) if format == HistoryFormat::Parquet => Box::new(std::io::sink()),
+		Some(path) => {
+			let is_new = !std::path::Path::new(path).exists();
+			let mut file = std::fs::OpenOptions::new()
+				.create(true)
+				.append(true)
+				.open(path)
+				.expect("could not open output file");
+			if is_new && format == HistoryFormat::Csv {
+				writeln!(file, "{CSV_HEADER}").unwrap();
+			}
+			Box::new(file)
+		},
+		None => {
+			if format == HistoryFormat::Csv {
+				println!("{CSV_HEADER}");
+			}
+			Box::new(std::io::stdout())
+		},
+	};
+
+	let mut ends = CeremonyEnds::new(&api, head);
+	for cindex in from..=to {
+		if only_cid.is_some_and(|cid| done.contains(&(cid.to_string(), cindex))) {
+			info!("skipping cindex {}: already exported", cindex);
+			continue
+		}
+		let Some(end) = ends.get(cindex).await else {
+			warn!("ceremony {} has not ended yet, stopping", cindex);
+			break
+		};
+		let blocks = CeremonyBlocks {
+			start: match cindex.checked_sub(1) {
+				Some(previous) => ends.get(previous).await.unwrap_or(0),
+				None => 0,
+			},
+			end,
+			claims_end: ends.get(cindex + 1).await.unwrap_or(head),
+		};
+		let existing = api
+			.get_community_identifiers(Some(get_block_hash(&api, end).await))
+			.await
+			.unwrap_or_default();
+		let cids = existing.into_iter().filter(|c| only_cid.is_none_or(|cid| cid == *c));
+		for cid in cids {
+			if done.contains(&(cid.to_string(), cindex)) {
+				info!("skipping {} cindex {}: already exported", cid, cindex);
+				continue
+			}
+			info!("exporting {} cindex {} (end of cycle at block {})", cid, cindex, end);
+			let row = collect_row(&api, cid, cindex, &blocks).await;
+			match format {
+				HistoryFormat::Csv =>
+					writeln!(writer, "{}", row.to_csv()).expect("could not write row"),
+				HistoryFormat::Json => writeln!(writer, "{}", serde_json::to_string(&row).unwrap())
+					.expect("could not write row"),
+				HistoryFormat::Parquet => {
+					parquet_rows.push(row);
+					unwritten_rows += 1;
+					if unwritten_rows >= PARQUET_BATCH_ROWS {
+						write_parquet(parquet_out.unwrap(), &parquet_rows)
+							.expect("could not write parquet file");
+						unwritten_rows = 0;
+					}
+				},
+			}
+			writer.flush().unwrap();
+		}
+	}
+	if let (Some(path), true) = (parquet_out, unwritten_rows > 0) {
+		write_parquet(path, &parquet_rows).expect("could not write parquet file");
+	}
+}
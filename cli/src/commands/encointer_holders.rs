@@ -0,0 +1,85 @@
+//! Snapshot of all holders of a community currency, e.g. for airdrops and audits.
+
+use crate::{
+	cli::Cli,
+	commands::{
+		encointer_core::{
+			apply_demurrage, get_community_holders, get_community_issuance, get_demurrage_per_block,
+		},
+		frame::get_block_number,
+	},
+	utils::{get_chain_api, OutputFormat},
+};
+use encointer_api_client_extension::CommunitiesApi;
+use encointer_node_runtime::{BalanceType, BlockNumber};
+use log::info;
+use serde::Serialize;
+use sp_core::crypto::Ss58Codec;
+use std::{io::Write, str::FromStr};
+
+const CSV_HEADER: &str = "address,principal,last_update,balance";
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HolderRow {
+	pub address: String,
+	/// Balance at `last_update`, before demurrage.
+	pub principal: f64,
+	pub last_update: BlockNumber,
+	/// Balance at the snapshot block.
+	pub balance: f64,
+}
+
+pub async fn export_holders(cli: &Cli, format: &str, out: Option<&str>) {
+	let format = OutputFormat::from_str(format).unwrap();
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let cid = api
+		.verify_cid(cli.cid.as_deref().expect("please supply argument --cid"), maybe_at)
+		.await;
+	let bn = get_block_number(&api, maybe_at).await;
+	let demurrage = get_demurrage_per_block(&api, cid, maybe_at).await;
+
+	let mut total = BalanceType::from_num(0);
+	let mut rows: Vec<HolderRow> = get_community_holders(&api, cid, maybe_at)
+		.await
+		.into_iter()
+		.map(|(account, entry)| {
+			let (principal, last_update) = (entry.principal, entry.last_update);
+			let balance = apply_demurrage(entry, bn, demurrage);
+			total += balance;
+			HolderRow {
+				address: account.to_ss58check(),
+				principal: principal.to_num::<f64>(),
+				last_update,
+				balance: balance.to_num::<f64>(),
+			}
+		})
+		.collect();
+	rows.sort_by(|a, b| b.balance.total_cmp(&a.balance));
+	info!("{} holders of {} at block {}", rows.len(), cid, bn);
+
+	let mut writer: Box<dyn Write> = match out {
+		Some(path) => Box::new(std::fs::File::create(path).expect("could not create output file")),
+		None => Box::new(std::io::stdout()),
+	};
+	match format {
+		OutputFormat::Csv => {
+			writeln!(writer, "{CSV_HEADER}").unwrap();
+			for r in rows.iter() {
+				writeln!(writer, "{},{},{},{}", r.address, r.principal, r.last_update, r.balance)
+					.unwrap();
+			}
+		},
+		OutputFormat::Json =>
+			writeln!(writer, "{}", serde_json::to_string_pretty(&rows).unwrap()).unwrap(),
+	}
+	writer.flush().unwrap();
+
+	// the summary goes to stderr, so the export can be piped
+	let issuance = get_community_issuance(&api, &cid.to_string(), maybe_at).await;
+	eprintln!("holders: {}", rows.len());
+	eprintln!("sum of balances: {total}");
+	eprintln!("total issuance: {issuance}");
+	eprintln!("difference: {}", issuance - total);
+}
@@ -1,10 +1,16 @@
 //! IPFS upload command with sr25519 gateway authentication
 
-use crate::{cli::Cli, exit_code, utils::keys::get_pair_from_str};
+use crate::{
+	cli::Cli,
+	community_spec::{parse_community_spec, LintFinding, LintLevel},
+	exit_code,
+	utils::keys::get_pair_from_str,
+};
+use log::info;
 use reqwest::multipart;
 use serde::{Deserialize, Serialize};
 use sp_core::{crypto::Ss58Codec, Pair};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -47,12 +53,15 @@ struct UploadResponse {
 	size: String,
 }
 
-pub async fn ipfs_upload(cli: &Cli, signer_str: &str, gateway: &str, file_path: &str) {
-	let cid = cli.cid.as_deref().expect("--cid required");
-
+/// Authenticates with the gateway as a CC holder of `cid` and returns the JWT.
+async fn authenticate(
+	client: &reqwest::Client,
+	gateway: &str,
+	signer_str: &str,
+	cid: &str,
+) -> String {
 	let pair = get_pair_from_str(signer_str);
 	let address = format!("{}", pair.public().to_ss58check());
-	let client = reqwest::Client::new();
 
 	// Request challenge
 	let challenge_resp = client
@@ -121,6 +130,14 @@ pub async fn ipfs_upload(cli: &Cli, signer_str: &str, gateway: &str, file_path:
 		})
 		.unwrap();
 
+	token.token
+}
+
+pub async fn ipfs_upload(cli: &Cli, signer_str: &str, gateway: &str, file_path: &str) {
+	let cid = cli.cid.as_deref().expect("--cid required");
+	let client = reqwest::Client::new();
+	let token = authenticate(&client, gateway, signer_str, cid).await;
+
 	// Upload file
 	let file_bytes = std::fs::read(file_path)
 		.map_err(|e| {
@@ -135,7 +152,7 @@ pub async fn ipfs_upload(cli: &Cli, signer_str: &str, gateway: &str, file_path:
 
 	let upload_resp = client
 		.post(format!("{}/ipfs/add", gateway))
-		.bearer_auth(&token.token)
+		.bearer_auth(&token)
 		.multipart(form)
 		.send()
 		.await
@@ -161,3 +178,239 @@ pub async fn ipfs_upload(cli: &Cli, signer_str: &str, gateway: &str, file_path:
 
 	println!("{}", result.hash);
 }
+
+/// Icon sizes in px the app expects, by resolution subfolder of `icons`.
+const ICON_SIZES: [(&str, u32); 3] = [("", 36), ("2.0x", 72), ("3.0x", 108)];
+const ICON_NAME: &str = "community_icon";
+const THEME_FILE: &str = "theme.json";
+
+/// Width and height of a PNG image, read from its IHDR chunk.
+fn png_dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+	const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+	if bytes.len() < 24 || bytes[..8] != SIGNATURE || &bytes[12..16] != b"IHDR" {
+		return None
+	}
+	let be_u32 = |b: &[u8]| u32::from_be_bytes([b[0], b[1], b[2], b[3]]);
+	Some((be_u32(&bytes[16..20]), be_u32(&bytes[20..24])))
+}
+
+/// Checks the layout produced by `publish-assets.py`: `icons/community_icon.svg`, or
+/// `icons/[2.0x/|3.0x/]community_icon.png` in all resolutions, and an optional `theme.json`.
+pub fn validate_asset_folder(folder: &Path) -> Vec<LintFinding> {
+	let mut findings = Vec::new();
+	let icons = folder.join("icons");
+	let svg = icons.join(format!("{ICON_NAME}.svg"));
+	let has_svg = svg.is_file();
+	if has_svg {
+		match std::fs::read_to_string(&svg) {
+			Ok(content) if content.contains("<svg") => (),
+			_ => findings.push(LintFinding::error("icons/community_icon.svg", "not an svg image")),
+		}
+	}
+	for (subfolder, size) in ICON_SIZES {
+		let png = icons.join(subfolder).join(format!("{ICON_NAME}.png"));
+		let path = png.strip_prefix(folder).unwrap().display().to_string();
+		let Ok(bytes) = std::fs::read(&png) else {
+			if !has_svg {
+				findings
+					.push(LintFinding::error(path, format!("missing, or provide {ICON_NAME}.svg")));
+			}
+			continue
+		};
+		match png_dimensions(&bytes) {
+			Some((w, h)) if w == size && h == size => (),
+			Some((w, h)) => findings
+				.push(LintFinding::error(path, format!("is {w}x{h} px, must be {size}x{size}"))),
+			None => findings.push(LintFinding::error(path, "not a png image")),
+		}
+	}
+	let theme = folder.join(THEME_FILE);
+	if theme.is_file() {
+		match std::fs::read_to_string(&theme)
+			.map_err(|e| e.to_string())
+			.and_then(|s| serde_json::from_str::<serde_json::Value>(&s).map_err(|e| e.to_string()))
+		{
+			Ok(serde_json::Value::Object(_)) => (),
+			Ok(_) => findings.push(LintFinding::error(THEME_FILE, "must be a json object")),
+			Err(e) => findings.push(LintFinding::error(THEME_FILE, e)),
+		}
+	} else {
+		findings.push(LintFinding::warning(
+			THEME_FILE,
+			"missing, the app's default theme will be used",
+		));
+	}
+	findings
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+	for entry in std::fs::read_dir(dir).unwrap_or_else(|e| panic!("could not read {dir:?}: {e}")) {
+		let path = entry.unwrap().path();
+		if path.is_dir() {
+			collect_files(&path, files);
+		} else {
+			files.push(path);
+		}
+	}
+}
+
+/// Validates and uploads a community asset folder as a directory, like `publish-assets.py`.
+pub async fn publish_assets(
+	cli: &Cli,
+	signer_str: &str,
+	gateway: &str,
+	folder: &str,
+	patch_spec: Option<&str>,
+	dryrun: bool,
+) {
+	let folder = Path::new(folder);
+	let findings = validate_asset_folder(folder);
+	for f in findings.iter() {
+		println!("{f}");
+	}
+	if findings.iter().any(|f| f.level == LintLevel::Error) {
+		std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+	}
+	if dryrun {
+		return
+	}
+
+	let cid = cli.cid.as_deref().expect("--cid required");
+	let client = reqwest::Client::new();
+	let token = authenticate(&client, gateway, signer_str, cid).await;
+
+	// files are named by their path below the folder's name, so the gateway rebuilds the tree
+	let root = folder.canonicalize().expect("invalid asset folder");
+	let root_name = root.file_name().unwrap().to_str().unwrap().to_string();
+	let mut files = Vec::new();
+	collect_files(&root, &mut files);
+	let mut form = multipart::Form::new();
+	for file in files {
+		let name = format!("{root_name}/{}", file.strip_prefix(&root).unwrap().display());
+		let bytes = std::fs::read(&file).expect("could not read asset file");
+		form = form.part("file", multipart::Part::bytes(bytes).file_name(name));
+	}
+
+	let upload_resp = client
+		.post(format!("{}/ipfs/add", gateway))
+		.query(&[("pin", "true"), ("wrap-with-directory", "true")])
+		.bearer_auth(&token)
+		.multipart(form)
+		.send()
+		.await
+		.map_err(|e| {
+			eprintln!("Failed to upload: {}", e);
+			std::process::exit(exit_code::RPC_ERROR);
+		})
+		.unwrap();
+
+	if !upload_resp.status().is_success() {
+		eprintln!("Upload failed: {}", upload_resp.status());
+		std::process::exit(exit_code::RPC_ERROR);
+	}
+
+	// one json object per added file and directory, the wrapping directory comes last
+	let body = upload_resp.text().await.unwrap();
+	let assets_cid = body
+		.lines()
+		.rev()
+		.find_map(|l| serde_json::from_str::<UploadResponse>(l).ok())
+		.map(|r| r.hash)
+		.unwrap_or_else(|| {
+			eprintln!("Failed to parse upload response: {}", body);
+			std::process::exit(exit_code::RPC_ERROR);
+		});
+	println!("{assets_cid}");
+
+	if let Some(spec_file) = patch_spec {
+		let content = std::fs::read_to_string(spec_file).expect("could not read spec file");
+		let spec = parse_community_spec(&content).unwrap_or_else(|errors| {
+			for e in errors {
+				eprintln!("invalid community spec {spec_file}: {e}");
+			}
+			std::process::exit(exit_code::INVALID_COMMUNITY_SPEC);
+		});
+		if spec.community.is_none() {
+			panic!("spec has no community section");
+		}
+		// patch the file as is, to keep its format version and foreign members
+		let mut value: serde_json::Value = serde_json::from_str(&content).unwrap();
+		value["community"]["meta"]["assets"] = assets_cid.into();
+		std::fs::write(spec_file, serde_json::to_string_pretty(&value).unwrap())
+			.expect("could not write spec file");
+		info!("updated community.meta.assets in {}", spec_file);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A png header claiming the given dimensions, enough for `png_dimensions`.
+	fn png(width: u32, height: u32) -> Vec<u8> {
+		let mut bytes = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0, 0, 0, 13];
+		bytes.extend_from_slice(b"IHDR");
+		bytes.extend_from_slice(&width.to_be_bytes());
+		bytes.extend_from_slice(&height.to_be_bytes());
+		bytes.extend_from_slice(&[8, 6, 0, 0, 0]);
+		bytes
+	}
+
+	fn asset_folder(name: &str) -> PathBuf {
+		let folder =
+			std::env::temp_dir().join(format!("encointer-assets-{}-{name}", std::process::id()));
+		let _ = std::fs::remove_dir_all(&folder);
+		for (subfolder, _) in ICON_SIZES {
+			std::fs::create_dir_all(folder.join("icons").join(subfolder)).unwrap();
+		}
+		folder
+	}
+
+	fn errors(findings: &[LintFinding]) -> Vec<String> {
+		findings
+			.iter()
+			.filter(|f| f.level == LintLevel::Error)
+			.map(|f| f.to_string())
+			.collect()
+	}
+
+	#[test]
+	fn png_dimensions_reads_ihdr() {
+		assert_eq!(png_dimensions(&png(72, 36)), Some((72, 36)));
+		assert_eq!(png_dimensions(&png(72, 36)[..23]), None);
+		assert_eq!(png_dimensions(b"<svg xmlns=\"http://www.w3.org/2000/svg\"/>"), None);
+	}
+
+	#[test]
+	fn validate_asset_folder_accepts_pngs_in_all_resolutions() {
+		let folder = asset_folder("pngs");
+		for (subfolder, size) in ICON_SIZES {
+			let icon = folder.join("icons").join(subfolder).join(format!("{ICON_NAME}.png"));
+			std::fs::write(icon, png(size, size)).unwrap();
+		}
+		std::fs::write(folder.join(THEME_FILE), "{}").unwrap();
+		assert!(validate_asset_folder(&folder).is_empty());
+		std::fs::remove_dir_all(folder).unwrap();
+	}
+
+	#[test]
+	fn validate_asset_folder_accepts_svg_and_warns_about_missing_theme() {
+		let folder = asset_folder("svg");
+		std::fs::write(folder.join("icons").join(format!("{ICON_NAME}.svg")), "<svg/>").unwrap();
+		let findings = validate_asset_folder(&folder);
+		assert!(errors(&findings).is_empty());
+		assert_eq!(findings.len(), 1);
+		std::fs::remove_dir_all(folder).unwrap();
+	}
+
+	#[test]
+	fn validate_asset_folder_reports_wrong_and_missing_icons() {
+		let folder = asset_folder("wrong");
+		std::fs::write(folder.join("icons").join(format!("{ICON_NAME}.png")), png(72, 72)).unwrap();
+		std::fs::write(folder.join("icons/2.0x").join(format!("{ICON_NAME}.png")), "not a png")
+			.unwrap();
+		std::fs::write(folder.join(THEME_FILE), "[]").unwrap();
+		assert_eq!(errors(&validate_asset_folder(&folder)).len(), 4);
+		std::fs::remove_dir_all(folder).unwrap();
+	}
+}
@@ -0,0 +1,300 @@
+//! Offline selection of meetup locations from a geojson extract of public places.
+//!
+//! Candidates are the points of the places file within the bounding polygon which the chain
+//! accepts as locations and which keep the chain's distance constraints to all existing locations. Out of these, locations are picked by
+//! farthest point sampling: the next location is always the candidate farthest away from all
+//! locations picked so far, which spreads them evenly over the area.
+
+use crate::community_spec::{
+	is_near_dateline, read_community_spec_from_file, solar_trip_time_s, CommunitySpec,
+	CommunitySpecFile, LocationFeature, COMMUNITY_SPEC_VERSION,
+};
+use encointer_api_client_extension::haversine_distance;
+use encointer_primitives::communities::{consts::MAX_ABS_LATITUDE, Degree, Location};
+use geojson::{GeoJson, Geometry, Value};
+use log::{info, warn};
+
+/// Distance constraints a new location has to keep to all other locations.
+#[derive(Debug, Clone, Copy)]
+pub struct LocationConstraints {
+	pub min_solar_trip_time_s: u32,
+	pub max_speed_mps: u32,
+	pub min_distance_m: u32,
+}
+
+impl LocationConstraints {
+	fn allows(&self, a: &Location, b: &Location) -> bool {
+		haversine_distance(a, b) >= self.min_distance_m &&
+			solar_trip_time_s(a, b, self.max_speed_mps) >= self.min_solar_trip_time_s
+	}
+}
+
+struct Candidate {
+	location: Location,
+	name: Option<String>,
+}
+
+fn read_geojson(path: &str) -> GeoJson {
+	let content =
+		std::fs::read_to_string(path).unwrap_or_else(|e| panic!("could not read {path}: {e}"));
+	GeoJson::from_json_value(serde_json::from_str(&content).expect("invalid json"))
+		.unwrap_or_else(|e| panic!("{path} is not valid geojson: {e}"))
+}
+
+/// Polygons as lists of rings of [lon, lat], the first ring being the exterior.
+fn read_polygons(path: &str) -> Vec<Vec<Vec<Vec<f64>>>> {
+	let geometries: Vec<Geometry> = match read_geojson(path) {
+		GeoJson::Geometry(g) => vec![g],
+		GeoJson::Feature(f) => f.geometry.into_iter().collect(),
+		GeoJson::FeatureCollection(c) =>
+			c.features.into_iter().filter_map(|f| f.geometry).collect(),
+	};
+	let polygons: Vec<_> = geometries
+		.into_iter()
+		.flat_map(|g| match g.value {
+			Value::Polygon(p) => vec![p],
+			Value::MultiPolygon(mp) => mp,
+			_ => vec![],
+		})
+		.collect();
+	if polygons.is_empty() {
+		panic!("{path} contains no Polygon or MultiPolygon");
+	}
+	polygons
+}
+
+/// Ray casting point-in-ring test.
+fn ring_contains(ring: &[Vec<f64>], lon: f64, lat: f64) -> bool {
+	let mut inside = false;
+	for (a, b) in ring.iter().zip(ring.iter().cycle().skip(1)) {
+		if (a[1] > lat) != (b[1] > lat) && lon < (b[0] - a[0]) * (lat - a[1]) / (b[1] - a[1]) + a[0]
+		{
+			inside = !inside;
+		}
+	}
+	inside
+}
+
+fn polygons_contain(polygons: &[Vec<Vec<Vec<f64>>>], lon: f64, lat: f64) -> bool {
+	polygons.iter().any(|rings| match rings.split_first() {
+		Some((exterior, holes)) =>
+			ring_contains(exterior, lon, lat) && !holes.iter().any(|h| ring_contains(h, lon, lat)),
+		None => false,
+	})
+}
+
+/// All points of the places file. Other geometries are skipped.
+fn read_places(path: &str) -> Vec<Candidate> {
+	let features = match read_geojson(path) {
+		GeoJson::FeatureCollection(c) => c.features,
+		GeoJson::Feature(f) => vec![f],
+		GeoJson::Geometry(_) => panic!("{path} must contain features"),
+	};
+	let mut skipped = 0;
+	let candidates: Vec<Candidate> = features
+		.into_iter()
+		.filter_map(|f| match f.geometry.as_ref().map(|g| &g.value) {
+			Some(Value::Point(pt)) if pt.len() >= 2 => Some(Candidate {
+				location: Location { lon: Degree::from_num(pt[0]), lat: Degree::from_num(pt[1]) },
+				name: f
+					.properties
+					.as_ref()
+					.and_then(|p| p.get("name"))
+					.and_then(|n| n.as_str())
+					.map(|n| n.to_string()),
+			}),
+			_ => {
+				skipped += 1;
+				None
+			},
+		})
+		.collect();
+	if skipped > 0 {
+		warn!("skipped {} places which are not points", skipped);
+	}
+	candidates
+}
+
+/// Picks up to `count` well-spread locations out of `candidates` which keep `constraints` to each
+/// other and to `existing`.
+fn select_locations(
+	candidates: Vec<Candidate>,
+	existing: &[Location],
+	constraints: LocationConstraints,
+	count: usize,
+) -> Vec<Candidate> {
+	let mut candidates: Vec<Candidate> = candidates
+		.into_iter()
+		.filter(|c| existing.iter().all(|e| constraints.allows(&c.location, e)))
+		.collect();
+	info!("{} candidates keep their distance to existing locations", candidates.len());
+	if candidates.is_empty() || count == 0 {
+		return vec![]
+	}
+
+	// start close to the center of the candidates, so a small count still covers the middle
+	let n = candidates.len() as f64;
+	let center = Location {
+		lat: Degree::from_num(
+			candidates.iter().map(|c| c.location.lat.to_num::<f64>()).sum::<f64>() / n,
+		),
+		lon: Degree::from_num(
+			candidates.iter().map(|c| c.location.lon.to_num::<f64>()).sum::<f64>() / n,
+		),
+	};
+	let first = (0..candidates.len())
+		.min_by_key(|i| haversine_distance(&candidates[*i].location, &center))
+		.unwrap();
+
+	let mut selected = vec![candidates.swap_remove(first)];
+	// distance of every remaining candidate to the nearest selected location
+	let mut nearest = vec![u32::MAX; candidates.len()];
+	while selected.len() < count {
+		let last = selected[selected.len() - 1].location;
+		let mut i = 0;
+		while i < candidates.len() {
+			if constraints.allows(&candidates[i].location, &last) {
+				nearest[i] = nearest[i].min(haversine_distance(&candidates[i].location, &last));
+				i += 1;
+			} else {
+				candidates.swap_remove(i);
+				nearest.swap_remove(i);
+			}
+		}
+		let Some(next) = (0..candidates.len()).max_by_key(|i| nearest[*i]) else { break };
+		nearest.swap_remove(next);
+		selected.push(candidates.swap_remove(next));
+	}
+	selected
+}
+
+pub fn suggest_locations(
+	places_file: &str,
+	polygon_file: &str,
+	existing_files: &[String],
+	constraints: LocationConstraints,
+	count: usize,
+	out: Option<&str>,
+) {
+	let polygons = read_polygons(polygon_file);
+	let places = read_places(places_file);
+	let total = places.len();
+	let candidates: Vec<Candidate> = places
+		.into_iter()
+		.filter(|c| {
+			polygons_contain(
+				&polygons,
+				c.location.lon.to_num::<f64>(),
+				c.location.lat.to_num::<f64>(),
+			) && c.location.lat.abs() < MAX_ABS_LATITUDE &&
+				!is_near_dateline(&c.location)
+		})
+		.collect();
+	info!("{} of {} places are within the polygon", candidates.len(), total);
+
+	let existing: Vec<Location> = existing_files
+		.iter()
+		.flat_map(|path| read_community_spec_from_file(path).locations())
+		.collect();
+	info!("avoiding {} existing locations", existing.len());
+
+	let selected = select_locations(candidates, &existing, constraints, count);
+	if selected.len() < count {
+		warn!("only {} of {} requested locations could be placed", selected.len(), count);
+	}
+
+	let spec = CommunitySpecFile {
+		version: COMMUNITY_SPEC_VERSION,
+		geojson_type: "FeatureCollection".into(),
+		community: None,
+		features: selected
+			.iter()
+			.map(|c| {
+				let mut feature = LocationFeature::from_location(&c.location);
+				if let (Some(name), Some(properties)) = (&c.name, feature.properties.as_mut()) {
+					properties.insert("name".into(), name.clone().into());
+				}
+				feature
+			})
+			.collect(),
+	};
+	let json = serde_json::to_string_pretty(&spec).unwrap();
+	match out {
+		Some(path) => {
+			std::fs::write(path, json).expect("could not write locations file");
+			println!("{path}");
+		},
+		None => println!("{json}"),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CONSTRAINTS: LocationConstraints =
+		LocationConstraints { min_solar_trip_time_s: 1, max_speed_mps: 83, min_distance_m: 100 };
+
+	fn candidate(lat: f64, lon: f64) -> Candidate {
+		Candidate {
+			location: Location::new(Degree::from_num(lat), Degree::from_num(lon)),
+			name: None,
+		}
+	}
+
+	/// Candidates on a 10 x 10 grid with about 110 m spacing around Zurich.
+	fn grid() -> Vec<Candidate> {
+		(0..100)
+			.map(|i| candidate(47.37 + (i / 10) as f64 * 0.001, 8.54 + (i % 10) as f64 * 0.0015))
+			.collect()
+	}
+
+	#[test]
+	fn ring_contains_works() {
+		let square =
+			vec![vec![0.0, 0.0], vec![2.0, 0.0], vec![2.0, 2.0], vec![0.0, 2.0], vec![0.0, 0.0]];
+		assert!(ring_contains(&square, 1.0, 1.0));
+		assert!(!ring_contains(&square, 3.0, 1.0));
+		assert!(!ring_contains(&square, 1.0, -1.0));
+		// the ring need not be closed
+		assert!(ring_contains(&square[..4], 1.0, 1.0));
+		let with_hole =
+			vec![square, vec![vec![0.5, 0.5], vec![1.5, 0.5], vec![1.5, 1.5], vec![0.5, 1.5]]];
+		assert!(polygons_contain(&[with_hole.clone()], 0.25, 0.25));
+		assert!(!polygons_contain(&[with_hole], 1.0, 1.0));
+	}
+
+	#[test]
+	fn selected_locations_keep_constraints() {
+		let existing = [grid()[0].location];
+		let selected = select_locations(grid(), &existing, CONSTRAINTS, 20);
+		assert_eq!(selected.len(), 20);
+		let locations: Vec<Location> =
+			selected.iter().map(|c| c.location).chain(existing).collect();
+		for (i, a) in locations.iter().enumerate() {
+			for b in locations[i + 1..].iter() {
+				assert!(CONSTRAINTS.allows(a, b));
+			}
+		}
+	}
+
+	#[test]
+	fn select_locations_spreads_over_the_area() {
+		let selected = select_locations(grid(), &[], CONSTRAINTS, 5);
+		// the first pick is the candidate closest to the center, the next ones the corners
+		let grid = grid();
+		assert_eq!(selected[0].location, grid[44].location);
+		let corners: Vec<Location> = [0, 9, 90, 99].iter().map(|i| grid[*i].location).collect();
+		for c in selected[1..].iter() {
+			assert!(corners.contains(&c.location));
+		}
+	}
+
+	#[test]
+	fn select_locations_stops_when_no_candidate_is_left() {
+		let crowded =
+			vec![candidate(47.37, 8.54), candidate(47.3701, 8.54), candidate(47.38, 8.54)];
+		assert_eq!(select_locations(crowded, &[], CONSTRAINTS, 3).len(), 2);
+		assert!(select_locations(grid(), &[], CONSTRAINTS, 0).is_empty());
+	}
+}
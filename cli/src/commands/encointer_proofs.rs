@@ -0,0 +1,404 @@
+//! Portable bundles of proofs of attendance.
+//!
+//! A bundle collects `ProofOfAttendance`s of one prover across several ceremonies and communities
+//! and is signed by the prover. It is plain JSON, so third parties can verify it without our
+//! tooling:
+//!
+//! * every proof is valid if `attendeeSignature` is an sr25519 signature by `attendee` over
+//!   `SCALE((prover: AccountId32, ceremonyIndex: u32))`, which is what the ceremonies pallet checks
+//!   when a proof is used to register as reputable.
+//! * the bundle is valid if `signature` is an sr25519 signature by `prover` over
+//!   `SCALE((version: u8, genesisHash: H256, challenge: Vec<u8>, proofs: Vec<ProofOfAttendance>))`.
+//! * whether `attendee` really attended is only known on chain: `EncointerCeremonies::
+//!   ParticipantReputation((cid, ceremonyIndex), attendee)` must be verified.
+
+use crate::{
+	cli::Cli,
+	commands::encointer_ceremonies::{get_reputation, get_reputation_history, prove_attendance},
+	community_spec::parse_community_identifier,
+	exit_code,
+	utils::{
+		get_chain_api,
+		keys::{get_accountid_from_str, get_pair_from_str},
+	},
+};
+use encointer_api_client_extension::CommunitiesApi;
+use encointer_node_runtime::{AccountId, Hash, Signature};
+use encointer_primitives::{
+	ceremonies::{CeremonyIndexType, ProofOfAttendance, Reputation},
+	communities::CommunityIdentifier,
+};
+use log::{debug, error, info, warn};
+use parity_scale_codec::Encode;
+use serde::{Deserialize, Serialize};
+use sp_core::{crypto::Ss58Codec, sr25519 as sr25519_core, Pair, H256};
+use sp_runtime::traits::Verify;
+
+const BUNDLE_FORMAT: &str = "encointer-proof-of-attendance-bundle";
+const BUNDLE_VERSION: u8 = 1;
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProofBundle {
+	pub format: String,
+	pub version: u8,
+	/// Genesis hash of the chain the proofs refer to.
+	pub genesis_hash: String,
+	/// Arbitrary challenge chosen by the verifier to prevent replay, hex encoded.
+	pub challenge: String,
+	pub prover: String,
+	pub proofs: Vec<BundledProof>,
+	/// Signature of the prover over the bundle payload, see module docs.
+	pub signature: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BundledProof {
+	pub community_identifier: String,
+	pub ceremony_index: CeremonyIndexType,
+	pub attendee: String,
+	pub attendee_signature: String,
+}
+
+#[derive(Debug)]
+pub struct ProofVerification {
+	pub community_identifier: CommunityIdentifier,
+	pub ceremony_index: CeremonyIndexType,
+	pub attendee: AccountId,
+	pub signature_valid: bool,
+	/// `None` if not checked on chain.
+	pub reputation: Option<Reputation>,
+}
+
+impl ProofVerification {
+	pub fn is_valid(&self) -> bool {
+		self.signature_valid &&
+			self.reputation.as_ref().is_none_or(|r| {
+				matches!(r, Reputation::VerifiedUnlinked | Reputation::VerifiedLinked(_))
+			})
+	}
+}
+
+fn bundle_payload(
+	genesis_hash: H256,
+	challenge: &[u8],
+	proofs: &[ProofOfAttendance<Signature, AccountId>],
+) -> Vec<u8> {
+	(BUNDLE_VERSION, genesis_hash, challenge.to_vec(), proofs.to_vec()).encode()
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, String> {
+	hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("invalid hex in {field}: {e}"))
+}
+
+fn decode_signature(field: &str, value: &str) -> Result<Signature, String> {
+	let bytes: [u8; 64] = decode_hex(field, value)?
+		.try_into()
+		.map_err(|_| format!("{field} must be a 64 byte sr25519 signature"))?;
+	Ok(Signature::from(sr25519_core::Signature::from_raw(bytes)))
+}
+
+fn decode_account(field: &str, value: &str) -> Result<AccountId, String> {
+	AccountId::from_ss58check(value).map_err(|e| format!("invalid address in {field}: {e:?}"))
+}
+
+impl ProofBundle {
+	/// Reconstructs the proofs contained in the bundle.
+	pub fn proofs_of_attendance(
+		&self,
+	) -> Result<Vec<ProofOfAttendance<Signature, AccountId>>, String> {
+		let prover = decode_account("prover", &self.prover)?;
+		self.proofs
+			.iter()
+			.map(|p| {
+				Ok(ProofOfAttendance {
+					prover_public: prover.clone(),
+					community_identifier: parse_community_identifier(&p.community_identifier)
+						.ok_or_else(|| {
+							format!("invalid communityIdentifier {}", p.community_identifier)
+						})?,
+					ceremony_index: p.ceremony_index,
+					attendee_public: decode_account("attendee", &p.attendee)?,
+					attendee_signature: decode_signature(
+						"attendeeSignature",
+						&p.attendee_signature,
+					)?,
+				})
+			})
+			.collect()
+	}
+
+	/// Checks the bundle signature and the signatures of all contained proofs. Works offline.
+	///
+	/// Returns the reason if the bundle is malformed, bundles come from untrusted third parties.
+	pub fn verify_signatures(&self) -> Result<(bool, Vec<ProofVerification>), String> {
+		if self.format != BUNDLE_FORMAT {
+			return Err(format!("unknown bundle format {}", self.format))
+		}
+		if self.version != BUNDLE_VERSION {
+			return Err(format!("unsupported bundle version {}", self.version))
+		}
+		let prover = decode_account("prover", &self.prover)?;
+		let genesis_hash: [u8; 32] = decode_hex("genesisHash", &self.genesis_hash)?
+			.try_into()
+			.map_err(|_| "genesisHash must be 32 bytes".to_string())?;
+		let proofs = self.proofs_of_attendance()?;
+
+		let payload = bundle_payload(
+			H256::from(genesis_hash),
+			&decode_hex("challenge", &self.challenge)?,
+			&proofs,
+		);
+		let bundle_valid =
+			decode_signature("signature", &self.signature)?.verify(&payload[..], &prover);
+
+		let verifications = proofs
+			.into_iter()
+			.map(|p| ProofVerification {
+				signature_valid: p.attendee_signature.verify(
+					&(p.prover_public.clone(), p.ceremony_index).encode()[..],
+					&p.attendee_public,
+				),
+				community_identifier: p.community_identifier,
+				ceremony_index: p.ceremony_index,
+				attendee: p.attendee_public,
+				reputation: None,
+			})
+			.collect();
+		Ok((bundle_valid, verifications))
+	}
+}
+
+/// Bundles the sr25519 signed `proofs` and signs the bundle. Other proofs are skipped, as the
+/// bundle format only supports sr25519 signatures.
+fn sign_bundle(
+	prover_pair: &sr25519_core::Pair,
+	genesis_hash: H256,
+	challenge: &[u8],
+	proofs: Vec<ProofOfAttendance<Signature, AccountId>>,
+) -> ProofBundle {
+	let proofs: Vec<_> = proofs
+		.into_iter()
+		.filter(|p| {
+			let is_sr25519 = matches!(p.attendee_signature, Signature::Sr25519(_));
+			if !is_sr25519 {
+				warn!(
+					"skipping proof of {} for cindex {}, only sr25519 signatures can be bundled",
+					p.attendee_public, p.ceremony_index
+				);
+			}
+			is_sr25519
+		})
+		.collect();
+	let payload = bundle_payload(genesis_hash, challenge, &proofs);
+	ProofBundle {
+		format: BUNDLE_FORMAT.into(),
+		version: BUNDLE_VERSION,
+		genesis_hash: format!("0x{}", hex::encode(genesis_hash)),
+		challenge: format!("0x{}", hex::encode(challenge)),
+		prover: AccountId::from(prover_pair.public()).to_ss58check(),
+		proofs: proofs
+			.iter()
+			.filter_map(|p| match &p.attendee_signature {
+				Signature::Sr25519(s) => Some(BundledProof {
+					community_identifier: p.community_identifier.to_string(),
+					ceremony_index: p.ceremony_index,
+					attendee: p.attendee_public.to_ss58check(),
+					attendee_signature: format!("0x{}", hex::encode(s.0)),
+				}),
+				_ => None,
+			})
+			.collect(),
+		signature: format!("0x{}", hex::encode(prover_pair.sign(&payload).0)),
+	}
+}
+
+pub async fn export_proof_bundle(
+	cli: &Cli,
+	prover_str: &str,
+	attendees: &[String],
+	ceremony_indexes: &[CeremonyIndexType],
+	challenge: Option<&str>,
+	out: Option<&str>,
+) {
+	let api = get_chain_api(cli).await;
+	let prover = get_accountid_from_str(prover_str);
+	let maybe_cid = match cli.cid.as_deref() {
+		Some(cid) => Some(api.verify_cid(cid, None).await),
+		None => None,
+	};
+	let attendees =
+		if attendees.is_empty() { vec![prover_str.to_string()] } else { attendees.to_vec() };
+
+	let mut proofs = Vec::new();
+	for attendee_str in attendees.iter() {
+		let attendee = get_accountid_from_str(attendee_str);
+		let history = get_reputation_history(&api, &attendee, None).await.unwrap_or_default();
+		for (cindex, rep) in history {
+			if !matches!(
+				rep.reputation,
+				Reputation::VerifiedUnlinked | Reputation::VerifiedLinked(_)
+			) {
+				continue
+			}
+			if maybe_cid.is_some_and(|cid| cid != rep.community_identifier) ||
+				(!ceremony_indexes.is_empty() && !ceremony_indexes.contains(&cindex))
+			{
+				continue
+			}
+			debug!(
+				"adding proof for {} at {}, cindex {}",
+				attendee, rep.community_identifier, cindex
+			);
+			proofs.push(prove_attendance(
+				prover.clone(),
+				rep.community_identifier,
+				cindex,
+				attendee_str,
+			));
+		}
+	}
+	if proofs.is_empty() {
+		error!("no verified reputation found to export");
+		std::process::exit(exit_code::INVALID_REPUTATION);
+	}
+	info!("exporting {} proofs of attendance for {}", proofs.len(), prover);
+
+	let challenge = challenge.map(|c| c.as_bytes().to_vec()).unwrap_or_default();
+	let genesis_hash: Hash = api.genesis_hash();
+	let prover_pair = sr25519_core::Pair::from(get_pair_from_str(prover_str));
+	let bundle = sign_bundle(&prover_pair, genesis_hash, &challenge, proofs);
+	if bundle.proofs.is_empty() {
+		error!("no proof with an sr25519 signature to export");
+		std::process::exit(exit_code::INVALID_REPUTATION);
+	}
+
+	let json = serde_json::to_string_pretty(&bundle).unwrap();
+	match out {
+		Some(path) => {
+			std::fs::write(path, json).expect("could not write bundle");
+			println!("{path}");
+		},
+		None => println!("{json}"),
+	}
+}
+
+pub async fn verify_proof_bundle(cli: &Cli, file: &str, challenge: Option<&str>, onchain: bool) {
+	let content = std::fs::read_to_string(file).expect("could not read bundle file");
+	let bundle: ProofBundle = match serde_json::from_str(&content) {
+		Ok(bundle) => bundle,
+		Err(e) => invalid_bundle(&format!("not a proof bundle: {e}")),
+	};
+	let (bundle_valid, mut verifications) = match bundle.verify_signatures() {
+		Ok(result) => result,
+		Err(reason) => invalid_bundle(&reason),
+	};
+
+	let mut valid = true;
+	if let Some(c) = challenge {
+		// the challenge was decoded successfully when verifying signatures
+		if decode_hex("challenge", &bundle.challenge).unwrap_or_default() != c.as_bytes() {
+			println!("challenge mismatch");
+			valid = false;
+		}
+	}
+
+	println!("prover: {}", bundle.prover);
+	println!("bundle signature valid: {bundle_valid}");
+	valid &= bundle_valid;
+
+	if onchain {
+		let api = get_chain_api(cli).await;
+		if format!("0x{}", hex::encode(api.genesis_hash())) != bundle.genesis_hash {
+			println!("bundle was made for another chain (genesis {})", bundle.genesis_hash);
+			valid = false;
+		}
+		for v in verifications.iter_mut() {
+			v.reputation = Some(
+				get_reputation(&api, &v.attendee, v.community_identifier, v.ceremony_index, None)
+					.await,
+			);
+		}
+	}
+
+	println!("cid, cindex, attendee, signature valid, reputation, valid");
+	for v in verifications.iter() {
+		println!(
+			"{}, {}, {}, {}, {}, {}",
+			v.community_identifier,
+			v.ceremony_index,
+			v.attendee.to_ss58check(),
+			v.signature_valid,
+			v.reputation.as_ref().map_or("unchecked".to_string(), |r| format!("{r:?}")),
+			v.is_valid()
+		);
+		valid &= v.is_valid();
+	}
+
+	if !valid {
+		std::process::exit(exit_code::INVALID_PROOF);
+	}
+}
+
+fn invalid_bundle(reason: &str) -> ! {
+	println!("invalid bundle: {reason}");
+	std::process::exit(exit_code::INVALID_PROOF);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use sp_core::ed25519;
+
+	fn cid() -> CommunityIdentifier {
+		parse_community_identifier("u0qj944rhWE").unwrap()
+	}
+
+	fn bundle(proofs: Vec<ProofOfAttendance<Signature, AccountId>>) -> ProofBundle {
+		let prover_pair = sr25519_core::Pair::from(get_pair_from_str("//Alice"));
+		sign_bundle(&prover_pair, H256::repeat_byte(1), b"challenge", proofs)
+	}
+
+	fn alice() -> AccountId {
+		get_accountid_from_str("//Alice")
+	}
+
+	#[test]
+	fn signed_bundle_verifies_after_json_round_trip() {
+		let proofs = vec![
+			prove_attendance(alice(), cid(), 3, "//Alice"),
+			prove_attendance(alice(), cid(), 4, "//Bob"),
+		];
+		let json = serde_json::to_string(&bundle(proofs)).unwrap();
+		let bundle: ProofBundle = serde_json::from_str(&json).unwrap();
+		let (bundle_valid, verifications) = bundle.verify_signatures().unwrap();
+		assert!(bundle_valid);
+		assert_eq!(verifications.len(), 2);
+		assert!(verifications.iter().all(|v| v.is_valid()));
+		assert_eq!(verifications[1].attendee, get_accountid_from_str("//Bob"));
+	}
+
+	#[test]
+	fn tampered_bundle_does_not_verify() {
+		let mut bundle = bundle(vec![prove_attendance(alice(), cid(), 3, "//Bob")]);
+		bundle.proofs[0].ceremony_index = 4;
+		let (bundle_valid, verifications) = bundle.verify_signatures().unwrap();
+		assert!(!bundle_valid);
+		assert!(!verifications[0].signature_valid);
+
+		bundle.proofs[0].community_identifier = "u0qj9".into();
+		assert!(bundle.verify_signatures().is_err());
+	}
+
+	#[test]
+	fn non_sr25519_proofs_are_skipped() {
+		let mut ed25519_proof = prove_attendance(alice(), cid(), 3, "//Bob");
+		ed25519_proof.attendee_signature =
+			Signature::from(ed25519::Pair::from_string("//Bob", None).unwrap().sign(b"proof"));
+		let bundle = bundle(vec![ed25519_proof, prove_attendance(alice(), cid(), 4, "//Bob")]);
+		assert_eq!(bundle.proofs.len(), 1);
+		assert_eq!(bundle.proofs[0].ceremony_index, 4);
+		assert!(bundle.verify_signatures().unwrap().0);
+	}
+}
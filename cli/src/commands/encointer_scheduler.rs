@@ -1,21 +1,25 @@
 use crate::{
 	cli::Cli,
-	commands::frame::get_block_number,
+	commands::frame::{get_block_hash, get_block_number, get_events_in_range},
 	utils::{
 		collective_propose_call, contains_sudo_pallet, get_chain_api, get_councillors,
 		keys::get_pair_from_str, print_raw_call, send_and_wait_for_in_block, sudo_call, xt,
 		OpaqueCall,
 	},
 };
+use chrono::{prelude::*, Utc};
 use encointer_api_client_extension::{
-	set_api_extrisic_params_builder, ParentchainExtrinsicSigner, SchedulerApi,
+	set_api_extrisic_params_builder, Api, CeremoniesApi, CommunitiesApi,
+	ParentchainExtrinsicSigner, SchedulerApi,
 };
-use encointer_node_runtime::Moment;
+use encointer_ceremonies_assignment::meetup_time;
+use encointer_node_runtime::{BlockNumber, Hash, Moment, ONE_DAY};
+use encointer_primitives::{ceremonies::CeremonyIndexType, scheduler::CeremonyPhaseType};
 
-use log::{debug, info};
+use log::{debug, info, warn};
 
 use sp_keyring::Sr25519Keyring as AccountKeyring;
-use substrate_api_client::ac_compose_macros::compose_call;
+use substrate_api_client::{ac_compose_macros::compose_call, GetStorage};
 
 pub async fn get_phase(cli: &Cli) {
 	let api = get_chain_api(cli).await;
@@ -79,3 +83,191 @@ pub async fn next_phase(cli: &Cli, signer_arg: Option<&str>) {
 	let phase = api.get_current_phase(None).await.unwrap();
 	println!("Phase is now: {phase:?}");
 }
+
+/// Start of a ceremony phase.
+#[derive(Debug, Clone)]
+pub struct PhaseStart {
+	pub cindex: CeremonyIndexType,
+	pub phase: CeremonyPhaseType,
+	pub start: Moment,
+}
+
+fn next_phase_of(phase: CeremonyPhaseType) -> CeremonyPhaseType {
+	match phase {
+		CeremonyPhaseType::Registering => CeremonyPhaseType::Assigning,
+		CeremonyPhaseType::Assigning => CeremonyPhaseType::Attesting,
+		CeremonyPhaseType::Attesting => CeremonyPhaseType::Registering,
+	}
+}
+
+/// Position of a phase in the sequence of all phases, which only increases over time.
+fn phase_ordinal(cindex: CeremonyIndexType, phase: CeremonyPhaseType) -> u64 {
+	let offset = match phase {
+		CeremonyPhaseType::Registering => 0,
+		CeremonyPhaseType::Assigning => 1,
+		CeremonyPhaseType::Attesting => 2,
+	};
+	cindex as u64 * 3 + offset
+}
+
+/// Timestamp of the block in which the phase current at `maybe_at` started, found by bisection.
+///
+/// `None` if the phase did not change since genesis or the state of older blocks is pruned.
+async fn get_current_phase_start(api: &Api, maybe_at: Option<Hash>) -> Option<Moment> {
+	let ordinal_at = |at: Option<Hash>| async move {
+		let cindex: Option<CeremonyIndexType> =
+			api.get_storage("EncointerScheduler", "CurrentCeremonyIndex", at).await.ok()?;
+		let phase: Option<CeremonyPhaseType> =
+			api.get_storage("EncointerScheduler", "CurrentPhase", at).await.ok()?;
+		Some(phase_ordinal(cindex.unwrap_or_default(), phase.unwrap_or_default()))
+	};
+	let current = ordinal_at(maybe_at).await?;
+	// find the first block in the current phase
+	let (mut lo, mut hi) = (0, get_block_number(api, maybe_at).await);
+	while lo < hi {
+		let mid = lo + (hi - lo) / 2;
+		if ordinal_at(Some(get_block_hash(api, mid).await)).await? >= current {
+			hi = mid;
+		} else {
+			lo = mid + 1;
+		}
+	}
+	if lo == 0 {
+		return None
+	}
+	api.get_storage("Timestamp", "Now", Some(get_block_hash(api, lo).await))
+		.await
+		.ok()?
+}
+
+/// Computes the phase starts of the next `cycles` cycles, beginning with the current phase.
+///
+/// Upcoming phases are derived from `NextPhaseTimestamp`, which already includes any
+/// `CeremonySchedulePushedByOneDay` shifts. Later pushes are not predictable. The current phase
+/// starts with the block which changed to it, as pushes shift it against its duration.
+pub async fn get_upcoming_phases(
+	api: &Api,
+	cycles: u32,
+	maybe_at: Option<Hash>,
+) -> Vec<PhaseStart> {
+	let mut cindex = api.get_ceremony_index(maybe_at).await;
+	let mut phase = api.get_current_phase(maybe_at).await.unwrap();
+	let mut next_start = api.get_next_phase_timestamp(maybe_at).await.unwrap();
+	let start = match get_current_phase_start(api, maybe_at).await {
+		Some(start) => start,
+		None => {
+			warn!("start of the current phase unknown, estimating it from the phase duration");
+			next_start - api.get_phase_duration(phase, maybe_at).await.unwrap()
+		},
+	};
+	let mut phases = vec![PhaseStart { cindex, phase, start }];
+	while phases.len() < 1 + 3 * cycles as usize {
+		phase = next_phase_of(phase);
+		if phase == CeremonyPhaseType::Registering {
+			cindex += 1;
+		}
+		phases.push(PhaseStart { cindex, phase, start: next_start });
+		next_start += api.get_phase_duration(phase, maybe_at).await.unwrap();
+	}
+	phases
+}
+
+pub fn format_moment(moment: Moment) -> String {
+	DateTime::<Utc>::from_timestamp_millis(moment as i64)
+		.map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+		.unwrap_or_default()
+}
+
+fn ical_moment(moment: Moment) -> String {
+	DateTime::<Utc>::from_timestamp_millis(moment as i64)
+		.map(|dt| dt.format("%Y%m%dT%H%M%SZ").to_string())
+		.unwrap_or_default()
+}
+
+/// Local mean solar time of `moment` at `lon`, as hh:mm.
+fn local_solar_time(moment: Moment, lon: f64) -> String {
+	let day = ONE_DAY as i64;
+	let local = (moment as i64 + (lon / 360.0 * day as f64) as i64).rem_euclid(day);
+	format!("{:02}:{:02}", local / 3_600_000, local % 3_600_000 / 60_000)
+}
+
+pub async fn print_schedule(
+	cli: &Cli,
+	cycles: u32,
+	ical: Option<&str>,
+	from_block: Option<BlockNumber>,
+) {
+	let api = get_chain_api(cli).await;
+	let maybe_at = cli.at_block();
+	let phases = get_upcoming_phases(&api, cycles, maybe_at).await;
+	let offset = api.get_meetup_time_offset(maybe_at).await.unwrap().unwrap_or(0);
+	let (cid, locations) = match cli.cid.as_deref() {
+		Some(cid) => {
+			let cid = api.verify_cid(cid, maybe_at).await;
+			(Some(cid), api.get_locations(cid).await.unwrap())
+		},
+		None => (None, vec![]),
+	};
+
+	if let Some(from) = from_block {
+		let to = get_block_number(&api, maybe_at).await;
+		let pushes: Vec<(BlockNumber, ())> = get_events_in_range(
+			&api,
+			"EncointerScheduler",
+			"CeremonySchedulePushedByOneDay",
+			from,
+			to,
+		)
+		.await;
+		for (block, _) in pushes.iter() {
+			println!("ceremony schedule was pushed by one day in block {block}");
+		}
+	}
+
+	let now = ical_moment(Utc::now().timestamp_millis() as Moment);
+	let mut events = Vec::new();
+	println!("cindex, phase, start");
+	for p in phases.iter() {
+		println!("{}, {:?}, {}", p.cindex, p.phase, format_moment(p.start));
+		events.push(format!(
+			"BEGIN:VEVENT\r\nUID:{}-{:?}@encointer\r\nDTSTAMP:{now}\r\nDTSTART:{}\r\nSUMMARY:Encointer ceremony {} {:?} phase\r\nEND:VEVENT\r\n",
+			p.cindex,
+			p.phase,
+			ical_moment(p.start),
+			p.cindex,
+			p.phase
+		));
+	}
+
+	if let Some(cid) = cid {
+		println!("meetup times for {} locations of {cid}", locations.len());
+		println!("cindex, lat, lon, meetup time, local solar time");
+		for p in phases.iter().filter(|p| p.phase == CeremonyPhaseType::Attesting) {
+			for location in locations.iter() {
+				let time = meetup_time(*location, p.start, ONE_DAY, offset);
+				let (lat, lon) = (location.lat.to_num::<f64>(), location.lon.to_num::<f64>());
+				println!(
+					"{}, {lat}, {lon}, {}, {}",
+					p.cindex,
+					format_moment(time),
+					local_solar_time(time, lon)
+				);
+				events.push(format!(
+					"BEGIN:VEVENT\r\nUID:{cid}-{}-{lat}-{lon}@encointer\r\nDTSTAMP:{now}\r\nDTSTART:{}\r\nGEO:{lat};{lon}\r\nSUMMARY:Encointer meetup {cid} ceremony {}\r\nEND:VEVENT\r\n",
+					p.cindex,
+					ical_moment(time),
+					p.cindex
+				));
+			}
+		}
+	}
+
+	if let Some(path) = ical {
+		let calendar = format!(
+			"BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//encointer//encointer-cli//EN\r\n{}END:VCALENDAR\r\n",
+			events.concat()
+		);
+		std::fs::write(path, calendar).expect("could not write calendar");
+		info!("wrote {} events to {}", events.len(), path);
+	}
+}
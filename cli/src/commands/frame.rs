@@ -7,13 +7,13 @@ use encointer_api_client_extension::{
 	set_api_extrisic_params_builder, Api, EncointerXt, ExtrinsicAddress, ParentchainExtrinsicSigner,
 };
 use encointer_node_runtime::{AccountId, BlockNumber, Hash};
-use log::{debug, info};
-use parity_scale_codec::{Compact, Encode};
+use log::{debug, info, warn};
+use parity_scale_codec::{Compact, Decode, Encode};
 use sp_keyring::Sr25519Keyring as AccountKeyring;
 use substrate_api_client::{
 	ac_compose_macros::{compose_call, compose_extrinsic_offline},
 	extrinsic::BalancesExtrinsics,
-	GetBalance, GetChainInfo, GetTransactionPayment, SubmitAndWatch, XtStatus,
+	FetchEvents, GetBalance, GetChainInfo, GetTransactionPayment, SubmitAndWatch, XtStatus,
 };
 
 pub async fn print_metadata(cli: &Cli) {
@@ -64,6 +64,45 @@ pub async fn get_block_number(api: &Api, maybe_at: Option<Hash>) -> BlockNumber
 	hdr.number
 }
 
+pub async fn get_block_hash(api: &Api, block_number: BlockNumber) -> Hash {
+	api.get_block_hash(Some(block_number))
+		.await
+		.unwrap()
+		.unwrap_or_else(|| panic!("block {block_number} not found"))
+}
+
+/// Collects all `pallet::variant` events emitted in blocks `from..=to` and decodes their fields.
+///
+/// Decoding is done with the current metadata, blocks whose events can't be fetched are skipped.
+pub async fn get_events_in_range<E: Decode>(
+	api: &Api,
+	pallet: &str,
+	variant: &str,
+	from: BlockNumber,
+	to: BlockNumber,
+) -> Vec<(BlockNumber, E)> {
+	let mut found = Vec::new();
+	for block_number in from..=to {
+		let hash = get_block_hash(api, block_number).await;
+		let events = match api.fetch_events_from_block(hash).await {
+			Ok(events) => events,
+			Err(e) => {
+				warn!("could not fetch events of block {}: {:?}", block_number, e);
+				continue
+			},
+		};
+		for event in events.iter().flatten() {
+			if event.pallet_name() == pallet && event.variant_name() == variant {
+				match E::decode(&mut event.field_bytes()) {
+					Ok(fields) => found.push((block_number, fields)),
+					Err(e) => warn!("could not decode {}::{}: {:?}", pallet, variant, e),
+				}
+			}
+		}
+	}
+	found
+}
+
 async fn reasonable_native_balance(api: &Api) -> u128 {
 	let alice: AccountId = AccountKeyring::Alice.into();
 	let xt = api.balance_transfer_allow_death(alice.into(), 9999).await.unwrap();
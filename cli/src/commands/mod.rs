@@ -1,11 +1,20 @@
+pub mod encointer_analytics;
+pub mod encointer_announcements;
+pub mod encointer_autopilot;
 pub mod encointer_bazaar;
 pub mod encointer_ceremonies;
+pub mod encointer_cid;
 pub mod encointer_communities;
+pub mod encointer_community_health;
 pub mod encointer_core;
 pub mod encointer_democracy;
 pub mod encointer_faucet;
+pub mod encointer_history;
+pub mod encointer_holders;
 pub mod encointer_ipfs;
+pub mod encointer_location_suggest;
 pub mod encointer_offline_payment;
+pub mod encointer_proofs;
 pub mod encointer_reputation_commitments;
 pub mod encointer_reputation_rings;
 pub mod encointer_scheduler;
@@ -21,6 +30,7 @@ pub async fn run(cli: &Cli) {
 		Commands::Account(cmd) => cmd.run(cli).await,
 		Commands::Community(cmd) => cmd.run(cli).await,
 		Commands::Ceremony(cmd) => cmd.run(cli).await,
+		Commands::Cid(cmd) => cmd.run(cli).await,
 		Commands::Democracy(cmd) => cmd.run(cli).await,
 		Commands::Bazaar(cmd) => cmd.run(cli).await,
 		Commands::Faucet(cmd) => cmd.run(cli).await,
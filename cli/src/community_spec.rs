@@ -1,21 +1,393 @@
-use encointer_node_runtime::AccountId;
+//! The community spec file: an enhanced geojson FeatureCollection of meetup locations with a
+//! `community` section describing the community.
+//!
+//! `community spec schema` prints the JSON Schema of the format, which is also committed as
+//! `community-spec.schema.json`. Older versions of the format are upgraded when they are read,
+//! `community spec migrate` rewrites a file in the current version.
+
+use encointer_api_client_extension::haversine_distance;
+use encointer_node_runtime::{AccountId, Runtime};
 use encointer_primitives::{
 	balances::{BalanceType, Demurrage},
 	common::{BoundedIpfsCid, FromStr, PalletString},
 	communities::{
-		AnnouncementSigner, CommunityIdentifier, CommunityMetadata, CommunityRules, Degree,
-		Location,
+		consts::MAX_ABS_LATITUDE, AnnouncementSigner, CommunityIdentifier, CommunityMetadata,
+		CommunityRules, Degree, Location,
 	},
 	fixed::transcendental::ln,
 };
-use geojson::GeoJson;
-use log::{debug, info};
+use frame_support::traits::Get;
+use log::{debug, error, info};
 use parity_scale_codec::Encode;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use sp_core::{crypto::Ss58Codec, sr25519, Pair};
 use substrate_api_client::{ac_compose_macros::compose_call, ac_node_api::Metadata};
 
-pub fn read_community_spec_from_file(path: &str) -> serde_json::Value {
+/// An error in a spec file, with the JSON path of the offending value.
+#[derive(Debug, Clone)]
+pub struct SpecError {
+	pub path: String,
+	pub message: String,
+}
+
+impl std::fmt::Display for SpecError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}: {}", self.path, self.message)
+	}
+}
+
+impl SpecError {
+	pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+		Self { path: path.into(), message: message.into() }
+	}
+}
+
+/// The spec format version written by this client.
+///
+/// Files without a `version` are version 1. Version 2 adds `community.cid`, which pins the
+/// community identifier: it is derived from the order of the bootstrappers, so two revisions of a
+/// spec which only reorder them describe different communities.
+pub const COMMUNITY_SPEC_VERSION: u32 = 2;
+
+/// A geojson FeatureCollection. Foreign members like `bbox` are allowed and ignored.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct CommunitySpecFile {
+	/// Format version. Files without it are version 1 and are upgraded when read.
+	pub version: u32,
+	/// Always "FeatureCollection".
+	#[serde(rename = "type")]
+	pub geojson_type: String,
+	/// Missing in files which only specify locations.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub community: Option<CommunitySection>,
+	/// The meetup locations. The first one determines the community identifier.
+	pub features: Vec<LocationFeature>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CommunitySection {
+	/// The community identifier. If set, it must match the one derived from the first location
+	/// and the bootstrappers in their order.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub cid: Option<String>,
+	pub meta: MetaSection,
+	/// SS58 addresses (or dev seeds like //Alice) of the bootstrappers.
+	pub bootstrappers: Vec<String>,
+	/// Number of blocks after which demurrage halves a balance. Defaults to the chain's default.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub demurrage_halving_blocks: Option<u64>,
+	/// Nominal income per ceremony. Defaults to the chain's default.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub ceremony_income: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MetaSection {
+	pub name: String,
+	pub symbol: String,
+	/// IPFS cid of the community's asset folder.
+	pub assets: String,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub theme: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub url: Option<String>,
+	#[serde(default)]
+	#[schemars(with = "Option<AnnouncementSignerSchema>")]
+	pub announcement_signer: Option<AnnouncementSigner>,
+	#[schemars(with = "CommunityRulesSchema")]
+	pub rules: CommunityRules,
+}
+
+// `AnnouncementSigner` and `CommunityRules` don't implement `JsonSchema`, so their serde
+// representation is mirrored here. `schema_mirrors_match_serde` keeps them in sync.
+
+/// Key which signs community announcements.
+#[derive(JsonSchema)]
+#[schemars(rename = "AnnouncementSigner", rename_all = "camelCase")]
+#[allow(dead_code)]
+enum AnnouncementSignerSchema {
+	/// Hex encoded 32 byte public key, optionally 0x prefixed.
+	Bip340(#[schemars(regex(pattern = r"^(0x)?[0-9a-fA-F]{64}$"))] String),
+}
+
+#[derive(JsonSchema)]
+#[schemars(rename = "CommunityRules", rename_all = "camelCase")]
+#[allow(dead_code)]
+enum CommunityRulesSchema {
+	LoCo,
+	LoCoFlex,
+	BeeDance,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct LocationFeature {
+	/// Always "Feature".
+	#[serde(rename = "type")]
+	pub geojson_type: String,
+	/// Free-form, may be null as geojson allows.
+	#[serde(default)]
+	pub properties: Option<Map<String, Value>>,
+	pub geometry: PointGeometry,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct PointGeometry {
+	/// Always "Point".
+	#[serde(rename = "type")]
+	pub geojson_type: String,
+	/// [longitude, latitude] or [longitude, latitude, altitude] in degrees and m.
+	#[serde(deserialize_with = "deserialize_position")]
+	#[schemars(length(min = 2, max = 3))]
+	pub coordinates: Vec<f64>,
+}
+
+fn deserialize_position<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+	let position = Vec::<f64>::deserialize(deserializer)?;
+	if !(2..=3).contains(&position.len()) {
+		return Err(serde::de::Error::invalid_length(position.len(), &"2 or 3 coordinates"))
+	}
+	Ok(position)
+}
+
+impl PointGeometry {
+	pub fn lon(&self) -> f64 {
+		self.coordinates[0]
+	}
+
+	pub fn lat(&self) -> f64 {
+		self.coordinates[1]
+	}
+}
+
+impl LocationFeature {
+	pub fn from_location(location: &Location) -> Self {
+		Self {
+			geojson_type: "Feature".into(),
+			properties: Some(Default::default()),
+			geometry: PointGeometry {
+				geojson_type: "Point".into(),
+				coordinates: vec![location.lon.to_num::<f64>(), location.lat.to_num::<f64>()],
+			},
+		}
+	}
+
+	pub fn location(&self) -> Location {
+		Location {
+			lon: Degree::from_num(self.geometry.lon()),
+			lat: Degree::from_num(self.geometry.lat()),
+		}
+	}
+}
+
+/// Deserializes `value` as `T`, recording the error with its path instead of failing.
+fn check<T: DeserializeOwned>(value: &Value, path: &str, errors: &mut Vec<SpecError>) -> Option<T> {
+	serde_path_to_error::deserialize(value)
+		.map_err(|e| {
+			let inner = e.path().to_string();
+			let path = match inner.as_str() {
+				"." if path.is_empty() => inner,
+				"." => path.to_string(),
+				i if i.starts_with('[') || path.is_empty() => format!("{path}{i}"),
+				i => format!("{path}.{i}"),
+			};
+			errors.push(SpecError::new(path, e.inner().to_string()))
+		})
+		.ok()
+}
+
+/// Checks field `key` of `object` like [`check`] does, reporting missing required fields.
+fn check_field<T: DeserializeOwned>(
+	object: &Map<String, Value>,
+	key: &str,
+	path: &str,
+	errors: &mut Vec<SpecError>,
+) {
+	let path = if path.is_empty() { key.to_string() } else { format!("{path}.{key}") };
+	match object.get(key) {
+		Some(value) => {
+			check::<T>(value, &path, errors);
+		},
+		None =>
+			if serde_json::from_value::<T>(Value::Null).is_err() {
+				errors.push(SpecError::new(path, "missing"));
+			},
+	}
+}
+
+/// Checks that `value` is an object without other keys than `known`, unless `known` is empty.
+fn check_object<'a>(
+	value: &'a Value,
+	path: &str,
+	known: &[&str],
+	errors: &mut Vec<SpecError>,
+) -> Option<&'a Map<String, Value>> {
+	let Some(object) = value.as_object() else {
+		errors.push(SpecError::new(path, "must be an object"));
+		return None
+	};
+	for key in object.keys().filter(|k| !known.is_empty() && !known.contains(&k.as_str())) {
+		let key_path = if path.is_empty() { key.clone() } else { format!("{path}.{key}") };
+		errors.push(SpecError::new(key_path, "unknown field"));
+	}
+	Some(object)
+}
+
+/// Reports every invalid value of a spec, field by field, so one run shows all problems.
+fn collect_spec_errors(spec: &Value) -> Vec<SpecError> {
+	let mut errors = Vec::new();
+	let Some(root) = check_object(spec, "", &[], &mut errors) else { return errors };
+	check_field::<u32>(root, "version", "", &mut errors);
+	check_field::<String>(root, "type", "", &mut errors);
+	match root.get("features").map(|f| f.as_array()) {
+		Some(Some(features)) =>
+			for (i, feature) in features.iter().enumerate() {
+				check::<LocationFeature>(feature, &format!("features[{i}]"), &mut errors);
+			},
+		Some(None) => errors.push(SpecError::new("features", "must be an array")),
+		None => errors.push(SpecError::new("features", "missing")),
+	}
+
+	let Some(community) = root.get("community").filter(|c| !c.is_null()) else { return errors };
+	let Some(community) = check_object(
+		community,
+		"community",
+		&["cid", "meta", "bootstrappers", "demurrage_halving_blocks", "ceremony_income"],
+		&mut errors,
+	) else {
+		return errors
+	};
+	check_field::<Option<String>>(community, "cid", "community", &mut errors);
+	check_field::<Vec<String>>(community, "bootstrappers", "community", &mut errors);
+	check_field::<Option<u64>>(community, "demurrage_halving_blocks", "community", &mut errors);
+	check_field::<Option<f64>>(community, "ceremony_income", "community", &mut errors);
+	match community.get("meta") {
+		Some(meta) => match meta.as_object() {
+			Some(meta) => {
+				let path = "community.meta";
+				check_field::<String>(meta, "name", path, &mut errors);
+				check_field::<String>(meta, "symbol", path, &mut errors);
+				check_field::<String>(meta, "assets", path, &mut errors);
+				check_field::<Option<String>>(meta, "theme", path, &mut errors);
+				check_field::<Option<String>>(meta, "url", path, &mut errors);
+				check_field::<Option<AnnouncementSigner>>(
+					meta,
+					"announcementSigner",
+					path,
+					&mut errors,
+				);
+				check_field::<CommunityRules>(meta, "rules", path, &mut errors);
+			},
+			None => errors.push(SpecError::new("community.meta", "must be an object")),
+		},
+		None => errors.push(SpecError::new("community.meta", "missing")),
+	}
+	errors
+}
+
+/// Parses a community identifier, `None` where `CommunityIdentifier::from_str` would panic.
+pub fn parse_community_identifier(cid: &str) -> Option<CommunityIdentifier> {
+	let digest = bs58::decode(cid.get(5..)?).into_vec().ok()?;
+	if digest.len() != 4 {
+		return None
+	}
+	<CommunityIdentifier as std::str::FromStr>::from_str(cid).ok()
+}
+
+/// Parses an SS58 address or a dev seed like //Alice, `None` if it is neither.
+fn parse_account(account: &str) -> Option<AccountId> {
+	if account.starts_with("//") {
+		sr25519::Pair::from_string(account, None).ok().map(|p| p.public().into())
+	} else {
+		AccountId::from_ss58check(account).ok()
+	}
+}
+
+/// The identifier `new_community` derives from `location` and `bootstrappers` in their order.
+fn derive_community_identifier(
+	location: Location,
+	bootstrappers: &[String],
+) -> Option<CommunityIdentifier> {
+	let bootstrappers = bootstrappers.iter().map(|b| parse_account(b)).collect::<Option<_>>()?;
+	CommunityIdentifier::new(location, bootstrappers).ok()
+}
+
+/// Upgrades a spec of any older version to [`COMMUNITY_SPEC_VERSION`], keeping unknown keys.
+pub fn migrate_community_spec(mut spec: Value) -> Result<Value, SpecError> {
+	let Some(root) = spec.as_object_mut() else {
+		return Err(SpecError::new(".", "must be an object"))
+	};
+	let version = match root.get("version") {
+		None => 1,
+		Some(version) => version
+			.as_u64()
+			.filter(|v| *v > 0)
+			.ok_or_else(|| SpecError::new("version", "must be a positive integer"))?,
+	};
+	if version > COMMUNITY_SPEC_VERSION as u64 {
+		return Err(SpecError::new(
+			"version",
+			format!("{version} is newer than {COMMUNITY_SPEC_VERSION}, upgrade encointer-cli"),
+		))
+	}
+	if version < 2 {
+		// pin the identifier which version 1 implied
+		let location = root
+			.get("features")
+			.and_then(|f| f.get(0))
+			.and_then(|f| serde_json::from_value::<LocationFeature>(f.clone()).ok());
+		if let (Some(location), Some(Value::Object(community))) =
+			(location, root.get_mut("community"))
+		{
+			let cid = community
+				.get("bootstrappers")
+				.and_then(|b| serde_json::from_value::<Vec<String>>(b.clone()).ok())
+				.and_then(|b| derive_community_identifier(location.location(), &b));
+			if let Some(cid) = cid {
+				community.entry("cid").or_insert_with(|| cid.to_string().into());
+			}
+		}
+	}
+	root.insert("version".into(), COMMUNITY_SPEC_VERSION.into());
+	Ok(spec)
+}
+
+/// Parses a spec of any version, reporting the path of every invalid value.
+pub fn parse_community_spec(spec_str: &str) -> Result<CommunitySpecFile, Vec<SpecError>> {
+	let raw: Value = serde_json::from_str(spec_str).map_err(|e| {
+		vec![SpecError::new(format!("line {} column {}", e.line(), e.column()), e.to_string())]
+	})?;
+	let raw = migrate_community_spec(raw).map_err(|e| vec![e])?;
+	let mut errors = Vec::new();
+	match check::<CommunitySpecFile>(&raw, "", &mut errors) {
+		Some(spec) => match spec.community.as_ref().and_then(|c| c.cid.as_deref()) {
+			Some(cid) if parse_community_identifier(cid).is_none() =>
+				Err(vec![SpecError::new("community.cid", "invalid community identifier")]),
+			_ => Ok(spec),
+		},
+		None => {
+			let all = collect_spec_errors(&raw);
+			Err(if all.is_empty() { errors } else { all })
+		},
+	}
+}
+
+pub fn read_community_spec_from_file(path: &str) -> CommunitySpecFile {
 	let spec_str = std::fs::read_to_string(path).unwrap();
-	serde_json::from_str(&spec_str).unwrap()
+	parse_community_spec(&spec_str).unwrap_or_else(|errors| {
+		for e in errors {
+			error!("invalid community spec {path}: {e}");
+		}
+		std::process::exit(crate::exit_code::INVALID_COMMUNITY_SPEC);
+	})
+}
+
+/// The JSON Schema of the spec format, pretty printed.
+pub fn community_spec_json_schema() -> String {
+	serde_json::to_string_pretty(&schemars::schema_for!(CommunitySpecFile)).unwrap()
 }
 
 /// Helper functions to handle the community
@@ -29,8 +401,8 @@ pub trait CommunitySpec {
 	/// The community's metadata.
 	fn metadata(&self) -> CommunityMetadata;
 
-	/// The community field of the json
-	fn community(&self) -> &serde_json::Value;
+	/// The community section of the spec
+	fn community(&self) -> &CommunitySection;
 
 	/// The community's [CommunityIdentifier].
 	fn community_identifier(&self) -> CommunityIdentifier;
@@ -42,105 +414,83 @@ pub trait CommunitySpec {
 	fn ceremony_reward(&self) -> Option<BalanceType>;
 }
 
-impl CommunitySpec for serde_json::Value {
+impl CommunitySpec for CommunitySpecFile {
 	fn locations(&self) -> Vec<Location> {
-		let geoloc = GeoJson::from_json_value(self.clone()).unwrap();
-		let mut loc = vec![];
-
-		if let GeoJson::FeatureCollection(ref ctn) = geoloc {
-			for feature in &ctn.features {
-				let val = &feature.geometry.as_ref().unwrap().value;
-				if let geojson::Value::Point(pt) = val {
-					let l = Location { lon: Degree::from_num(pt[0]), lat: Degree::from_num(pt[1]) };
-					loc.push(l);
-					debug!("lon: {} lat {} => {:?}", pt[0], pt[1], l);
-				}
-			}
-		};
-
-		loc
+		self.features
+			.iter()
+			.map(|f| {
+				let l = f.location();
+				debug!("lon: {} lat {} => {:?}", l.lon, l.lat, l);
+				l
+			})
+			.collect()
 	}
 
 	fn bootstrappers(&self) -> Vec<AccountId> {
-		self["community"]["bootstrappers"]
-			.as_array()
-			.expect("bootstrappers must be array")
+		self.community()
+			.bootstrappers
 			.iter()
-			.map(|a| crate::utils::keys::get_accountid_from_str(a.as_str().unwrap()))
+			.map(|a| crate::utils::keys::get_accountid_from_str(a))
 			.collect()
 	}
 
 	fn metadata(&self) -> CommunityMetadata {
+		let meta = &self.community().meta;
 		CommunityMetadata {
-			name: PalletString::from_str(
-				&serde_json::from_value::<String>(self["community"]["meta"]["name"].clone())
-					.unwrap(),
-			)
-			.unwrap(),
-			symbol: PalletString::from_str(
-				&serde_json::from_value::<String>(self["community"]["meta"]["symbol"].clone())
-					.unwrap(),
-			)
-			.unwrap(),
-			assets: BoundedIpfsCid::from_str(
-				&serde_json::from_value::<String>(self["community"]["meta"]["assets"].clone())
-					.unwrap(),
-			)
-			.unwrap(),
-			theme: match serde_json::from_value::<String>(
-				self["community"]["meta"]["theme"].clone(),
-			) {
-				Ok(theme) => Some(BoundedIpfsCid::from_str(&theme).unwrap()),
-				Err(_) => None,
-			},
-			url: match serde_json::from_value::<String>(self["community"]["meta"]["url"].clone()) {
-				Ok(url) => Some(BoundedIpfsCid::from_str(&url).unwrap()),
-				Err(_) => None,
-			},
-			announcement_signer: serde_json::from_value::<Option<AnnouncementSigner>>(
-				self["community"]["meta"]["announcementSigner"].clone(),
-			)
-			.unwrap(),
-			rules: serde_json::from_value::<CommunityRules>(
-				self["community"]["meta"]["rules"].clone(),
-			)
-			.unwrap(),
+			name: PalletString::from_str(&meta.name).expect("community.meta.name too long"),
+			symbol: PalletString::from_str(&meta.symbol).expect("community.meta.symbol too long"),
+			assets: BoundedIpfsCid::from_str(&meta.assets).expect("community.meta.assets too long"),
+			theme: meta
+				.theme
+				.as_ref()
+				.map(|t| BoundedIpfsCid::from_str(t).expect("community.meta.theme too long")),
+			url: meta
+				.url
+				.as_ref()
+				.map(|u| BoundedIpfsCid::from_str(u).expect("community.meta.url too long")),
+			announcement_signer: meta.announcement_signer.clone(),
+			rules: meta.rules,
 		}
 	}
 
-	fn community(&self) -> &serde_json::Value {
-		&self["community"]
+	fn community(&self) -> &CommunitySection {
+		self.community.as_ref().expect("spec has no community section")
 	}
 
 	fn community_identifier(&self) -> CommunityIdentifier {
-		CommunityIdentifier::new(self.locations()[0], self.bootstrappers()).unwrap()
+		match &self.community().cid {
+			Some(cid) => parse_community_identifier(cid).expect("checked when parsing"),
+			None => CommunityIdentifier::new(self.locations()[0], self.bootstrappers()).unwrap(),
+		}
 	}
 
 	fn demurrage(&self) -> Option<Demurrage> {
-		match serde_json::from_value::<u64>(self["community"]["demurrage_halving_blocks"].clone()) {
-			Ok(demurrage_halving_blocks) => {
-				let demurrage_rate =
-					demurrage_per_block_from_halving_blocks(demurrage_halving_blocks);
-
-				log::info!(
-					"demurrage halving blocks: {} which translates to a rate of {} ",
-					demurrage_halving_blocks,
-					hex::encode(demurrage_rate.encode())
-				);
-				Some(demurrage_rate)
-			},
-			Err(_) => None,
-		}
+		self.community().demurrage_halving_blocks.map(|demurrage_halving_blocks| {
+			let demurrage_rate = demurrage_per_block_from_halving_blocks(demurrage_halving_blocks);
+
+			log::info!(
+				"demurrage halving blocks: {} which translates to a rate of {} ",
+				demurrage_halving_blocks,
+				hex::encode(demurrage_rate.encode())
+			);
+			demurrage_rate
+		})
 	}
 
 	fn ceremony_reward(&self) -> Option<BalanceType> {
-		match serde_json::from_value::<f64>(self["community"]["ceremony_income"].clone()) {
-			Ok(reward) => {
-				log::info!("ceremony income specified as {}", reward);
-				Some(BalanceType::from_num(reward))
-			},
-			Err(_) => None,
-		}
+		self.community().ceremony_income.map(|reward| {
+			log::info!("ceremony income specified as {}", reward);
+			BalanceType::from_num(reward)
+		})
+	}
+}
+
+impl CommunitySpecFile {
+	/// The identifier `new_community` will derive from the first location and the bootstrappers,
+	/// regardless of `community.cid`. `None` if they are invalid.
+	pub fn derived_community_identifier(&self) -> Option<CommunityIdentifier> {
+		let location = self.features.first()?.location();
+		derive_community_identifier(location, &self.community.as_ref()?.bootstrappers)
 	}
 }
 
@@ -205,6 +555,58 @@ pub fn remove_location_call(
 	compose_call!(metadata, "EncointerCommunities", "remove_location", cid, loc).unwrap()
 }
 
+pub type UpdateCommunityMetadataCall = ([u8; 2], CommunityIdentifier, CommunityMetadata);
+pub type UpdateDemurrageCall = ([u8; 2], CommunityIdentifier, Demurrage);
+pub type UpdateNominalIncomeCall = ([u8; 2], CommunityIdentifier, BalanceType);
+pub type PurgeCommunityCall = ([u8; 2], CommunityIdentifier);
+pub type SetU32ParameterCall = ([u8; 2], u32);
+
+/// Create an `update_community_metadata` call to be used in an extrinsic.
+pub fn update_community_metadata_call(
+	metadata: &Metadata,
+	cid: CommunityIdentifier,
+	community_metadata: CommunityMetadata,
+) -> UpdateCommunityMetadataCall {
+	compose_call!(
+		metadata,
+		"EncointerCommunities",
+		"update_community_metadata",
+		cid,
+		community_metadata
+	)
+	.unwrap()
+}
+
+/// Create an `update_demurrage` call to be used in an extrinsic.
+pub fn update_demurrage_call(
+	metadata: &Metadata,
+	cid: CommunityIdentifier,
+	demurrage: Demurrage,
+) -> UpdateDemurrageCall {
+	compose_call!(metadata, "EncointerCommunities", "update_demurrage", cid, demurrage).unwrap()
+}
+
+/// Create an `update_nominal_income` call to be used in an extrinsic.
+pub fn update_nominal_income_call(
+	metadata: &Metadata,
+	cid: CommunityIdentifier,
+	nominal_income: BalanceType,
+) -> UpdateNominalIncomeCall {
+	compose_call!(metadata, "EncointerCommunities", "update_nominal_income", cid, nominal_income)
+		.unwrap()
+}
+
+/// Create a `purge_community` call to be used in an extrinsic.
+pub fn purge_community_call(metadata: &Metadata, cid: CommunityIdentifier) -> PurgeCommunityCall {
+	compose_call!(metadata, "EncointerCommunities", "purge_community", cid).unwrap()
+}
+
+/// Create a call of `EncointerCommunities` which sets a global `u32` parameter, like
+/// `set_min_solar_trip_time_s` or `set_max_speed_mps`.
+pub fn set_u32_parameter_call(metadata: &Metadata, call: &str, value: u32) -> SetU32ParameterCall {
+	compose_call!(metadata, "EncointerCommunities", call, value).unwrap()
+}
+
 pub fn demurrage_per_block_from_halving_blocks(halving_blocks: u64) -> Demurrage {
 	ln::<Demurrage, Demurrage>(Demurrage::from_num(0.5))
 		.unwrap()
@@ -213,3 +615,396 @@ pub fn demurrage_per_block_from_halving_blocks(halving_blocks: u64) -> Demurrage
 		.checked_div(Demurrage::from_num(halving_blocks))
 		.unwrap()
 }
+
+/// Inverse of [demurrage_per_block_from_halving_blocks], rounded. `None` if there is no demurrage.
+pub fn halving_blocks_from_demurrage_per_block(demurrage: Demurrage) -> Option<u64> {
+	let rate = demurrage.to_num::<f64>();
+	(rate > 0.0).then(|| (std::f64::consts::LN_2 / rate).round() as u64)
+}
+
+/// Minimum number of bootstrappers the chain accepts for a new community.
+pub const MIN_BOOTSTRAPPERS: usize = 3;
+
+/// Distance in m within which a location is considered too close to the dateline.
+pub const DATELINE_DISTANCE_M: u32 = 1_000_000;
+
+/// Whether `location` is within [`DATELINE_DISTANCE_M`] of the dateline, which the chain rejects.
+pub fn is_near_dateline(location: &Location) -> bool {
+	let dateline = Location { lat: location.lat, lon: Degree::from_num(180) };
+	let antimeridian = Location { lat: location.lat, lon: Degree::from_num(-180) };
+	haversine_distance(location, &dateline).min(haversine_distance(location, &antimeridian)) <
+		DATELINE_DISTANCE_M
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintLevel {
+	Error,
+	Warning,
+}
+
+/// A problem found in a community spec.
+#[derive(Debug, Clone)]
+pub struct LintFinding {
+	pub level: LintLevel,
+	/// JSON path of the offending value.
+	pub path: String,
+	pub message: String,
+}
+
+impl LintFinding {
+	pub fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+		Self { level: LintLevel::Error, path: path.into(), message: message.into() }
+	}
+
+	pub fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+		Self { level: LintLevel::Warning, path: path.into(), message: message.into() }
+	}
+}
+
+impl std::fmt::Display for LintFinding {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let level = match self.level {
+			LintLevel::Error => "error",
+			LintLevel::Warning => "warning",
+		};
+		write!(f, "{level}: {}: {}", self.path, self.message)
+	}
+}
+
+/// Time in s it takes to travel between two locations, minus the difference in solar time.
+///
+/// Locations closer than the chain's `MinSolarTripTimeS` would allow attending two meetups.
+pub fn solar_trip_time_s(from: &Location, to: &Location, max_speed_mps: u32) -> u32 {
+	let travel_time = haversine_distance(from, to) / max_speed_mps.max(1);
+	// the sun needs 240 s per degree of longitude
+	let solar_time_difference = ((from.lon - to.lon).abs().to_num::<f64>() * 240.0).round() as u32;
+	travel_time.saturating_sub(solar_time_difference)
+}
+
+/// Checks everything that can be checked without the chain on a well-formed spec.
+pub fn lint_community_spec(spec: &CommunitySpecFile) -> Vec<LintFinding> {
+	let mut findings = Vec::new();
+
+	if spec.geojson_type != "FeatureCollection" {
+		findings.push(LintFinding::error("type", "must be \"FeatureCollection\""));
+	}
+	let mut locations = Vec::new();
+	for (i, feature) in spec.features.iter().enumerate() {
+		if feature.geometry.geojson_type != "Point" {
+			findings.push(LintFinding::error(format!("features[{i}].geometry"), "must be a Point"));
+			continue
+		}
+		let (lon, lat) = (feature.geometry.lon(), feature.geometry.lat());
+		if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+			findings.push(LintFinding::error(
+				format!("features[{i}].geometry.coordinates"),
+				format!("[{lon}, {lat}] is not a valid [lon, lat]"),
+			));
+			continue
+		}
+		if lat.abs() >= MAX_ABS_LATITUDE.to_num::<f64>() {
+			findings.push(LintFinding::error(
+				format!("features[{i}].geometry.coordinates"),
+				format!("latitude {lat} is too close to the poles, the chain will reject it"),
+			));
+			continue
+		}
+		locations.push((i, feature.location()));
+	}
+	if locations.is_empty() {
+		findings.push(LintFinding::error("features", "at least one location is required"));
+	}
+	for (n, (i, a)) in locations.iter().enumerate() {
+		for (j, b) in locations.iter().skip(n + 1) {
+			if a == b {
+				findings.push(LintFinding::error(
+					format!("features[{j}]"),
+					format!("duplicate of location features[{i}]"),
+				));
+			}
+		}
+		if is_near_dateline(a) {
+			findings.push(LintFinding::error(
+				format!("features[{i}]"),
+				"too close to the dateline, the chain will reject it",
+			));
+		}
+	}
+
+	let Some(community) = &spec.community else {
+		findings.push(LintFinding::error("community", "missing"));
+		return findings
+	};
+
+	let max_bootstrappers: u32 =
+		<Runtime as pallet_encointer_communities::Config>::MaxBootstrappers::get();
+	let max_bootstrappers = max_bootstrappers as usize;
+	if !(MIN_BOOTSTRAPPERS..=max_bootstrappers).contains(&community.bootstrappers.len()) {
+		findings.push(LintFinding::error(
+			"community.bootstrappers",
+			format!("the chain requires {MIN_BOOTSTRAPPERS} to {max_bootstrappers} bootstrappers"),
+		));
+	}
+	let mut seen = Vec::new();
+	for (i, b) in community.bootstrappers.iter().enumerate() {
+		let path = format!("community.bootstrappers[{i}]");
+		if b.starts_with("//") {
+			findings.push(LintFinding::warning(path, format!("{b} is a development account")));
+			continue
+		}
+		match AccountId::from_ss58check(b) {
+			Ok(a) if seen.contains(&a) =>
+				findings.push(LintFinding::error(path, "duplicate bootstrapper")),
+			Ok(a) => seen.push(a),
+			Err(e) => findings.push(LintFinding::error(path, format!("invalid address: {e:?}"))),
+		}
+	}
+	if let (Some(cid), Some(derived)) = (&community.cid, spec.derived_community_identifier()) {
+		if derived.to_string() != *cid {
+			findings.push(LintFinding::error(
+				"community.cid",
+				format!(
+					"{cid} does not match {derived}, which the chain derives from features[0] and \
+					 the bootstrappers in their order"
+				),
+			));
+		}
+	}
+
+	let meta = &community.meta;
+	let pallet_string = |findings: &mut Vec<LintFinding>, field: &str, s: &str| {
+		PalletString::from_str(s)
+			.map_err(|_| {
+				findings.push(LintFinding::error(format!("community.meta.{field}"), "too long"))
+			})
+			.ok()
+	};
+	let ipfs_cid = |findings: &mut Vec<LintFinding>, field: &str, s: &str| {
+		BoundedIpfsCid::from_str(s)
+			.map_err(|_| {
+				findings.push(LintFinding::error(format!("community.meta.{field}"), "too long"))
+			})
+			.ok()
+	};
+	let name = pallet_string(&mut findings, "name", &meta.name);
+	let symbol = pallet_string(&mut findings, "symbol", &meta.symbol);
+	let assets = ipfs_cid(&mut findings, "assets", &meta.assets);
+	let theme = meta.theme.as_ref().map(|s| ipfs_cid(&mut findings, "theme", s));
+	let url = meta.url.as_ref().map(|s| ipfs_cid(&mut findings, "url", s));
+	if let (Some(name), Some(symbol), Some(assets)) = (name, symbol, assets) {
+		let metadata = CommunityMetadata {
+			name,
+			symbol,
+			assets,
+			theme: theme.flatten(),
+			url: url.flatten(),
+			announcement_signer: meta.announcement_signer.clone(),
+			rules: meta.rules,
+		};
+		if let Err(e) = metadata.validate() {
+			findings.push(LintFinding::error("community.meta", format!("{e:?}")));
+		}
+	}
+
+	match community.demurrage_halving_blocks {
+		None => findings.push(LintFinding::warning(
+			"community.demurrage_halving_blocks",
+			"not set, the chain's default demurrage will be used",
+		)),
+		Some(0) => findings
+			.push(LintFinding::error("community.demurrage_halving_blocks", "must be positive")),
+		Some(_) => (),
+	}
+	match community.ceremony_income {
+		None => findings.push(LintFinding::warning(
+			"community.ceremony_income",
+			"not set, the chain's default income will be used",
+		)),
+		Some(income) if income <= 0.0 || !income.is_finite() => findings
+			.push(LintFinding::error("community.ceremony_income", "must be a positive number")),
+		Some(_) => (),
+	}
+
+	findings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn committed_schema_matches_code() {
+		assert_eq!(
+			include_str!("../community-spec.schema.json").trim_end(),
+			community_spec_json_schema(),
+			"run `encointer-cli community spec schema --out community-spec.schema.json`"
+		);
+	}
+
+	#[test]
+	fn schema_mirrors_match_serde() {
+		let schema: Value = serde_json::from_str(&community_spec_json_schema()).unwrap();
+		let rules = &schema["definitions"]["CommunityRules"]["enum"];
+		for rule in [CommunityRules::LoCo, CommunityRules::LoCoFlex, CommunityRules::BeeDance] {
+			let serialized = serde_json::to_value(rule).unwrap();
+			assert!(rules.as_array().unwrap().contains(&serialized), "{serialized} not in schema");
+		}
+
+		let key = format!("0x{}", "ab".repeat(32));
+		let signer: AnnouncementSigner =
+			serde_json::from_value(serde_json::json!({ "bip340": key })).unwrap();
+		assert_eq!(serde_json::to_value(signer).unwrap(), serde_json::json!({ "bip340": key }));
+		let variant = &schema["definitions"]["AnnouncementSigner"]["oneOf"][0];
+		assert_eq!(variant["required"], serde_json::json!(["bip340"]));
+	}
+
+	#[test]
+	fn test_data_specs_parse() {
+		for entry in std::fs::read_dir("test-data").unwrap() {
+			let path = entry.unwrap().path();
+			if path.extension().is_some_and(|e| e == "json") {
+				if let Err(errors) = parse_community_spec(&std::fs::read_to_string(&path).unwrap())
+				{
+					panic!("{}: {:?}", path.display(), errors);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn pinned_test_data_identifiers_match() {
+		let mut cids = Vec::new();
+		for file in ["leu.zuerich.json", "leu.zuerich.V1.json", "leu.zuerich.V2.json"] {
+			let spec = read_community_spec_from_file(&format!("test-data/{file}"));
+			assert_eq!(spec.derived_community_identifier(), Some(spec.community_identifier()));
+			cids.push(spec.community_identifier());
+		}
+		// the revisions only reorder the bootstrappers, which yields another community
+		cids.dedup();
+		assert_eq!(cids.len(), 3);
+	}
+
+	#[test]
+	fn migration_pins_the_identifier_and_keeps_foreign_members() {
+		let mut v1: Value =
+			serde_json::from_str(&spec_with(community(), serde_json::json!([8.5, 47.4]))).unwrap();
+		v1["bbox"] = serde_json::json!([8.0, 47.0, 9.0, 48.0]);
+		let v2 = migrate_community_spec(v1.clone()).unwrap();
+		assert_eq!(v2["version"], COMMUNITY_SPEC_VERSION);
+		assert_eq!(v2["bbox"], v1["bbox"]);
+		let spec = parse_community_spec(&v2.to_string()).unwrap();
+		assert_eq!(
+			spec.community().cid.as_deref(),
+			Some(spec.derived_community_identifier().unwrap().to_string().as_str())
+		);
+		assert_eq!(migrate_community_spec(v2.clone()).unwrap(), v2);
+	}
+
+	#[test]
+	fn unsupported_versions_are_rejected() {
+		let mut spec: Value =
+			serde_json::from_str(&spec_with(community(), serde_json::json!([8.5, 47.4]))).unwrap();
+		spec["version"] = (COMMUNITY_SPEC_VERSION + 1).into();
+		assert_eq!(parse_community_spec(&spec.to_string()).unwrap_err()[0].path, "version");
+		spec["version"] = 0.into();
+		assert_eq!(parse_community_spec(&spec.to_string()).unwrap_err()[0].path, "version");
+	}
+
+	#[test]
+	fn malformed_cids_are_rejected() {
+		for cid in ["", "u0qj", "u0qj9", "u0qj9QqA2Q1", "u0qj90OIl"] {
+			assert!(parse_community_identifier(cid).is_none(), "{cid}");
+		}
+		assert!(parse_community_identifier("u0qj9QqA2Q").is_some());
+		let mut community = community();
+		community["cid"] = "u0qj".into();
+		let errors = parse_community_spec(&spec_with(community, serde_json::json!([8.5, 47.4])))
+			.unwrap_err();
+		assert_eq!(errors[0].path, "community.cid");
+	}
+
+	fn spec_with(community: Value, coordinates: Value) -> String {
+		serde_json::json!({
+			"type": "FeatureCollection",
+			"community": community,
+			"features": [{
+				"type": "Feature",
+				"properties": {},
+				"geometry": { "type": "Point", "coordinates": coordinates },
+			}],
+		})
+		.to_string()
+	}
+
+	fn community() -> Value {
+		serde_json::json!({
+			"meta": { "name": "Test", "symbol": "TST", "assets": "Qm", "rules": "loCo" },
+			"bootstrappers": ["//Alice"],
+			"demurrage_halving_blocks": 1000,
+			"ceremony_income": 22,
+		})
+	}
+
+	#[test]
+	fn coordinates_accept_altitude() {
+		let spec =
+			parse_community_spec(&spec_with(community(), serde_json::json!([8.5, 47.4, 400.0])))
+				.unwrap();
+		assert_eq!(spec.features[0].geometry.lat(), 47.4);
+		assert!(parse_community_spec(&spec_with(community(), serde_json::json!([8.5]))).is_err());
+		assert!(parse_community_spec(&spec_with(
+			community(),
+			serde_json::json!([1.0, 2.0, 3.0, 4.0])
+		))
+		.is_err());
+	}
+
+	#[test]
+	fn lint_checks_chain_constraints() {
+		let errors = |spec: &str| {
+			lint_community_spec(&parse_community_spec(spec).unwrap())
+				.into_iter()
+				.filter(|f| f.level == LintLevel::Error)
+				.map(|f| f.path)
+				.collect::<Vec<_>>()
+		};
+		let mut community = community();
+		community["meta"]["assets"] = "QmSpwTDiKbRVqHaV2LvaUVkwZkkuF8w1BjTjwXD9PEzHnd".into();
+		community["bootstrappers"] = serde_json::json!(["//Alice", "//Bob", "//Charlie"]);
+		assert_eq!(
+			errors(&spec_with(community.clone(), serde_json::json!([8.5, 47.4]))),
+			Vec::<String>::new()
+		);
+		assert_eq!(
+			errors(&spec_with(community.clone(), serde_json::json!([8.5, 78.0]))),
+			["features[0].geometry.coordinates", "features"]
+		);
+		community["bootstrappers"] = serde_json::json!(["//Alice", "//Bob"]);
+		assert_eq!(
+			errors(&spec_with(community, serde_json::json!([8.5, -47.4]))),
+			["community.bootstrappers"]
+		);
+	}
+
+	#[test]
+	fn all_errors_are_reported() {
+		let mut community = community();
+		community["meta"]["rules"] = "unknown".into();
+		community["ceremony_income"] = "much".into();
+		community["ceremonyIncome"] = 22.into();
+		let errors = parse_community_spec(&spec_with(community, serde_json::json!([8.5])))
+			.unwrap_err()
+			.into_iter()
+			.map(|e| e.path)
+			.collect::<Vec<_>>();
+		assert_eq!(
+			errors,
+			[
+				"features[0].geometry.coordinates",
+				"community.ceremonyIncome",
+				"community.ceremony_income",
+				"community.meta.rules"
+			]
+		);
+	}
+}
@@ -35,9 +35,16 @@ mod exit_code {
 	pub const WRONG_PHASE: i32 = 50;
 	pub const FEE_PAYMENT_FAILED: i32 = 51;
 	pub const INVALID_REPUTATION: i32 = 52;
+	pub const NO_ENDORSEMENT_TICKETS: i32 = 53;
+	pub const INVALID_PROOF: i32 = 54;
+	pub const IMPLAUSIBLE_CLAIM: i32 = 55;
+	pub const PROPOSAL_CLOSED: i32 = 56;
+	pub const INVALID_POSITION: i32 = 57;
 	pub const RPC_ERROR: i32 = 60;
 	pub const NOT_CC_HOLDER: i32 = 61;
 	pub const NO_CID_SPECIFIED: i32 = 70;
+	pub const INVALID_COMMUNITY_SPEC: i32 = 71;
+	pub const INVALID_ANNOUNCEMENT: i32 = 72;
 }
 
 #[tokio::main]
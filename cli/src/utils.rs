@@ -66,6 +66,25 @@ impl FromStr for CallWrapping {
 	}
 }
 
+/// Output format for tabular data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+	Csv,
+	Json,
+}
+
+impl FromStr for OutputFormat {
+	type Err = &'static str;
+
+	fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+		match value {
+			"csv" => Ok(OutputFormat::Csv),
+			"json" => Ok(OutputFormat::Json),
+			_ => Err("unknown output format"),
+		}
+	}
+}
+
 /// ([pallet_index, call_index], threshold, Proposal,length_bound)
 ///
 /// `threshold` is the number of members. threshold < 1 will make the proposal be executed directly.